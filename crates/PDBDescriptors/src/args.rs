@@ -1,10 +1,41 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
+use metrics::{
+    ConfidenceSource, ConfidenceThresholds, ContactAtoms, ContactDefinition, ContactOrderOptions, DomainMethod, DomainOptions,
+    MatrixLayout, SegmentationOptions, ShortProteinPolicy,
+};
+use pdb_io::StructureFormat;
+
+use crate::matrices::{MatrixKind, MatrixOptions, MatrixOutput};
+use crate::HullAtoms;
 
 pub struct Config {
     pub num_cpus: usize,
     pub pdb_dir: String,
     pub output_file: String,
-    pub subset: Option<usize>
+    pub subset: Option<usize>,
+    pub format: Option<StructureFormat>,
+    pub lenient: bool,
+    pub residue_output: Option<String>,
+    pub shape: bool,
+    pub hull_atoms: HullAtoms,
+    pub packing: bool,
+    pub contact_order: ContactOrderOptions,
+    pub matrix: Option<MatrixOptions>,
+    pub confidence: bool,
+    pub segmentation: SegmentationOptions,
+    pub confidence_thresholds: ConfidenceThresholds,
+    pub domains: Option<DomainOptions>,
+    pub per_chain: bool,
+    pub per_domain: bool,
+}
+
+/// A moving average centred on each residue spans an odd number of residues.
+fn odd_window(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(window) if window % 2 == 1 => Ok(window),
+        Ok(window) => Err(format!("{} is even, the window is centred on each residue", window)),
+        Err(e) => Err(e.to_string()),
+    }
 }
 
 pub fn parse_arguments() -> Config {
@@ -25,7 +56,7 @@ pub fn parse_arguments() -> Config {
                 .short('i')
                 .long("input")
                 .value_name("INPUT_DIR")
-                .help("Path to the PDB directory")
+                .help("Path to a directory of PDB/mmCIF files and .tar shards, or to a single .tar shard")
                 .default_value("/datas/SIMON/AFDB_20_to_100/pdbs/") 
         )
         .arg(
@@ -41,9 +72,221 @@ pub fn parse_arguments() -> Config {
                 .short('s')
                 .long("subset")
                 .value_name("SUBSET")
-                .help("Number of random PDB files to process (optional)")
+                .help("Number of structures to process: random files first, then the first structures of tar shards (optional)")
                 .required(false) 
         )
+        .arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .value_name("FORMAT")
+                .help("Only read files of this format, e.g. when shards ship both .pdb.gz and .cif.gz (optional)")
+                .value_parser(["pdb", "cif"])
+                .required(false)
+        )
+        .arg(
+            Arg::new("lenient")
+                .long("lenient")
+                .help("Skip malformed records with a warning instead of failing on the whole file")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("residue_output")
+                .long("residue-output")
+                .value_name("RESIDUE_FILE")
+                .help("Also write per-residue values (backbone and side-chain dihedrals, Ramachandran region, rotamer) to this CSV file (optional)")
+                .required(false)
+        )
+        .arg(
+            Arg::new("shape")
+                .long("shape")
+                .help("Add gyration tensor columns: eigenvalues, asphericity, acylindricity and shape anisotropy")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("hull_atoms")
+                .long("hull-atoms")
+                .value_name("ATOMS")
+                .help("Atoms of the oriented bounding box and convex hull: CA or all heavy atoms")
+                .value_parser(["ca", "heavy"])
+                .default_value("ca")
+        )
+        .arg(
+            Arg::new("packing")
+                .long("packing")
+                .help("Add van der Waals and solvent-excluded volumes of the heavy atoms, and their ratio as packing density")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("contact_atoms")
+                .long("contact-atoms")
+                .value_name("ATOMS")
+                .help("Atoms defining residue contacts for Relative_CO and Absolute_CO (CB falls back to CA for glycine)")
+                .value_parser(["ca", "cb", "heavy"])
+                .default_value("heavy")
+        )
+        .arg(
+            Arg::new("contact_cutoff")
+                .long("contact-cutoff")
+                .value_name("ANGSTROM")
+                .help("Largest atom distance between residues in contact")
+                .default_value("6.0")
+        )
+        .arg(
+            Arg::new("contact_min_separation")
+                .long("contact-min-separation")
+                .value_name("RESIDUES")
+                .help("Smallest residue number difference of a contact")
+                .default_value("1")
+        )
+        .arg(
+            Arg::new("contact_min_length")
+                .long("contact-min-length")
+                .value_name("RESIDUES")
+                .help("Leave the contact order columns empty for models with fewer residues")
+                .default_value("0")
+        )
+        .arg(
+            Arg::new("matrix_output")
+                .long("matrix-output")
+                .value_name("PATH")
+                .help("Write a residue matrix per model: one .npy file per model in this directory, or a single .npz file with --matrix-sharded (optional)")
+                .required(false)
+        )
+        .arg(
+            Arg::new("matrix_kind")
+                .long("matrix-kind")
+                .value_name("KIND")
+                .help("Residue distances in A, or a 0/1 contact map")
+                .value_parser(["distances", "contacts"])
+                .default_value("distances")
+        )
+        .arg(
+            Arg::new("matrix_atoms")
+                .long("matrix-atoms")
+                .value_name("ATOMS")
+                .help("Atoms the residue distances are measured between, the closest pair for heavy atoms")
+                .value_parser(["ca", "cb", "heavy"])
+                .default_value("ca")
+        )
+        .arg(
+            Arg::new("matrix_layout")
+                .long("matrix-layout")
+                .value_name("LAYOUT")
+                .help("n x n matrix, or condensed upper triangle without the diagonal as in scipy pdist")
+                .value_parser(["full", "condensed"])
+                .default_value("full")
+        )
+        .arg(
+            Arg::new("matrix_cutoff")
+                .long("matrix-cutoff")
+                .value_name("ANGSTROM")
+                .help("Largest distance of a contact in the contact map")
+                .default_value("8.0")
+        )
+        .arg(
+            Arg::new("matrix_f16")
+                .long("matrix-f16")
+                .help("Store distances as half precision floats")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("matrix_sharded")
+                .long("matrix-sharded")
+                .help("Write every matrix to the single .npz file given by --matrix-output, as arrays named ID_MODEL")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("confidence")
+                .long("confidence")
+                .help("Add pLDDT segmentation columns: low-confidence segments, disordered termini and the longest confident stretch")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("disorder_threshold")
+                .long("disorder-threshold")
+                .value_name("PLDDT")
+                .help("Smoothed pLDDT below which residues are low confidence")
+                .default_value("50")
+        )
+        .arg(
+            Arg::new("confident_threshold")
+                .long("confident-threshold")
+                .value_name("PLDDT")
+                .help("Smoothed pLDDT from which residues are high confidence")
+                .default_value("70")
+        )
+        .arg(
+            Arg::new("plddt_window")
+                .long("plddt-window")
+                .value_name("RESIDUES")
+                .help("Width of the centred moving average smoothing pLDDT along each chain, odd; 1 to disable")
+                .value_parser(odd_window)
+                .default_value("5")
+        )
+        .arg(
+            Arg::new("min_disorder_segment")
+                .long("min-disorder-segment")
+                .value_name("RESIDUES")
+                .help("Shortest run of low-confidence residues counted as a segment")
+                .default_value("5")
+        )
+        .arg(
+            Arg::new("confidence_source")
+                .long("confidence-source")
+                .value_name("SOURCE")
+                .help("What the B-factor column holds: pLDDT of predicted models, or B-factors of experimental structures (fractions then count residues under each threshold)")
+                .value_parser(["plddt", "bfactor"])
+                .default_value("plddt")
+        )
+        .arg(
+            Arg::new("confidence_thresholds")
+                .long("confidence-thresholds")
+                .value_name("VALUES")
+                .help("Comma-separated thresholds of the confidence fraction columns (default: 50,70,90 for pLDDT, 30,50,80 for B-factors)")
+                .required(false)
+        )
+        .arg(
+            Arg::new("domains")
+                .long("domains")
+                .value_name("METHOD")
+                .help("Parse structural domains by spectral clustering of the CA contact graph, or of the PAE when a companion file exists, and add domain columns")
+                .value_parser(["contacts", "pae"])
+                .required(false)
+        )
+        .arg(
+            Arg::new("domain_min_size")
+                .long("domain-min-size")
+                .value_name("RESIDUES")
+                .help("Fewest residues of a domain")
+                .default_value("30")
+        )
+        .arg(
+            Arg::new("domain_max_cut")
+                .long("domain-max-cut")
+                .value_name("NCUT")
+                .help("Highest normalised cut at which a domain is still split in two")
+                .default_value("0.2")
+        )
+        .arg(
+            Arg::new("domain_min_plddt")
+                .long("domain-min-plddt")
+                .value_name("PLDDT")
+                .help("Leave residues under this pLDDT out of every domain, as disordered linkers (optional)")
+                .required(false)
+        )
+        .arg(
+            Arg::new("per_chain")
+                .long("per-chain")
+                .help("Add a row of descriptors for every chain, after the model row, and a Part column")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            Arg::new("per_domain")
+                .long("per-domain")
+                .help("Add a row of descriptors for every domain, after the model row, and a Part column; implies --domains contacts unless given")
+                .action(ArgAction::SetTrue)
+        )
         .get_matches();
 
             
@@ -59,12 +302,136 @@ pub fn parse_arguments() -> Config {
         .to_string();
     let subset: Option<usize> = matches.get_one::<String>("subset")
         .and_then(|s| s.parse::<usize>().ok()); 
+    let format = matches.get_one::<String>("format")
+        .map(|f| match f.as_str() {
+            "cif" => StructureFormat::Mmcif,
+            _ => StructureFormat::Pdb,
+        });
+    let lenient = matches.get_flag("lenient");
+    let residue_output = matches.get_one::<String>("residue_output").cloned();
+    let shape = matches.get_flag("shape");
+    let packing = matches.get_flag("packing");
+    let contact_atoms = match matches.get_one::<String>("contact_atoms").map(String::as_str) {
+        Some("ca") => ContactAtoms::Ca,
+        Some("cb") => ContactAtoms::Cb,
+        _ => ContactAtoms::HeavyAtoms,
+    };
+    let contact_cutoff: f32 = matches.get_one::<String>("contact_cutoff")
+        .unwrap()
+        .parse()
+        .expect("Invalid contact cutoff");
+    let contact_min_separation: i32 = matches.get_one::<String>("contact_min_separation")
+        .unwrap()
+        .parse()
+        .expect("Invalid minimum sequence separation");
+    let contact_min_length: usize = matches.get_one::<String>("contact_min_length")
+        .unwrap()
+        .parse()
+        .expect("Invalid minimum length");
+    let contact_order = ContactOrderOptions {
+        contacts: ContactDefinition { atoms: contact_atoms, cutoff: contact_cutoff, min_separation: contact_min_separation },
+        short_protein: if contact_min_length > 0 {
+            ShortProteinPolicy::SkipBelow(contact_min_length)
+        } else {
+            ShortProteinPolicy::Compute
+        },
+    };
+    let hull_atoms = match matches.get_one::<String>("hull_atoms").map(String::as_str) {
+        Some("heavy") => HullAtoms::Heavy,
+        _ => HullAtoms::Ca,
+    };
+    let matrix = matches.get_one::<String>("matrix_output").map(|path| {
+        let kind = match matches.get_one::<String>("matrix_kind").map(String::as_str) {
+            Some("contacts") => MatrixKind::ContactMap,
+            _ => MatrixKind::Distances,
+        };
+        let atoms = match matches.get_one::<String>("matrix_atoms").map(String::as_str) {
+            Some("cb") => ContactAtoms::Cb,
+            Some("heavy") => ContactAtoms::HeavyAtoms,
+            _ => ContactAtoms::Ca,
+        };
+        let layout = match matches.get_one::<String>("matrix_layout").map(String::as_str) {
+            Some("condensed") => MatrixLayout::Condensed,
+            _ => MatrixLayout::Full,
+        };
+        let cutoff: f32 = matches.get_one::<String>("matrix_cutoff")
+            .unwrap()
+            .parse()
+            .expect("Invalid contact map cutoff");
+        let output = if matches.get_flag("matrix_sharded") {
+            MatrixOutput::Sharded(path.into())
+        } else {
+            MatrixOutput::PerStructure(path.into())
+        };
+        MatrixOptions { kind, atoms, layout, cutoff, half: matches.get_flag("matrix_f16"), output }
+    });
+    let confidence = matches.get_flag("confidence");
+    let segmentation = SegmentationOptions {
+        low: matches.get_one::<String>("disorder_threshold")
+            .unwrap()
+            .parse()
+            .expect("Invalid disorder threshold"),
+        high: matches.get_one::<String>("confident_threshold")
+            .unwrap()
+            .parse()
+            .expect("Invalid confident threshold"),
+        window: *matches.get_one::<usize>("plddt_window").unwrap(),
+        min_segment: matches.get_one::<String>("min_disorder_segment")
+            .unwrap()
+            .parse()
+            .expect("Invalid minimum disorder segment"),
+    };
+    let confidence_source = match matches.get_one::<String>("confidence_source").map(String::as_str) {
+        Some("bfactor") => ConfidenceSource::BFactor,
+        _ => ConfidenceSource::Plddt,
+    };
+    let confidence_thresholds = ConfidenceThresholds {
+        source: confidence_source,
+        values: matches.get_one::<String>("confidence_thresholds")
+            .map_or_else(
+                || confidence_source.default_thresholds(),
+                |values| values.split(',').map(|v| v.trim().parse().expect("Invalid confidence threshold")).collect(),
+            ),
+    };
+    let per_chain = matches.get_flag("per_chain");
+    let per_domain = matches.get_flag("per_domain");
+    let domains = (per_domain || matches.contains_id("domains")).then(|| DomainOptions {
+        method: match matches.get_one::<String>("domains").map(String::as_str) {
+            Some("pae") => DomainMethod::Pae,
+            _ => DomainMethod::Contacts,
+        },
+        min_domain_size: matches.get_one::<String>("domain_min_size")
+            .unwrap()
+            .parse()
+            .expect("Invalid minimum domain size"),
+        max_cut: matches.get_one::<String>("domain_max_cut")
+            .unwrap()
+            .parse()
+            .expect("Invalid maximum normalised cut"),
+        min_plddt: matches.get_one::<String>("domain_min_plddt")
+            .map(|p| p.parse().expect("Invalid minimum domain pLDDT")),
+        ..Default::default()
+    });
 
 
     Config {
         num_cpus,
         pdb_dir,
         output_file,
-        subset
+        subset,
+        format,
+        lenient,
+        residue_output,
+        shape,
+        hull_atoms,
+        packing,
+        contact_order,
+        matrix,
+        confidence,
+        segmentation,
+        confidence_thresholds,
+        domains,
+        per_chain,
+        per_domain
     }
 }
@@ -1,38 +1,413 @@
 mod args;
+mod matrices;
+mod shards;
 
-use metrics::{radius_of_gyration, bounding_box_volume, contact_order, plddt_statistics, get_ca_atoms};
+use metrics::{radius_of_gyration, bounding_box_volume, contact_order, confidence_statistics, get_ca_atoms, model_chain_breaks, dssp,
+    backbone_dihedrals, ramachandran_summary, RamachandranRegion, side_chain_dihedrals, rotamer_summary, RotamerFit,
+    SideChainDihedrals, gyration_tensor, ShapeDescriptors, get_heavy_atoms, oriented_bounding_box, convex_hull,
+    molecular_volume, MolecularVolume, VolumeOptions, contact_order_with, ContactOrderOptions, chain_confidence,
+    SegmentationOptions, ConfidenceSource, ConfidenceStatistics, ConfidenceThresholds, pae_summary, PaeSummary, parse_domains,
+    pae_domain_summary, DomainOptions, Domains};
 use rayon::prelude::*;
-use std::path::Path;
+use std::collections::HashMap;
 use std::fs;
 use rand::prelude::*; 
 use rayon::ThreadPoolBuilder;
 use args::parse_arguments;
 use std::fs::OpenOptions;
 use std::io::Write;
-use pdb_io::parse_pdb;
+use pdb_io::{entry_id, is_pae_file, pae_entry_id, parse_structure_with_options, read_pae, Chain, Model, ParseOptions,
+    ParsedPDB, PredictedAlignedError, ResidueView, StructureFormat};
+use shards::{is_tar_shard, process_tar_shard};
+use matrices::{model_matrix, MatrixOptions, MatrixWriter, ModelMatrix};
+use std::path::Path;
+
+
+/// Atoms the rotation-invariant size descriptors are computed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HullAtoms {
+    #[default]
+    Ca,
+    /// Every non-hydrogen polymer atom
+    Heavy,
+}
+
+/// Optional outputs selected on the command line.
+#[derive(Debug, Clone, Default)]
+pub struct DescriptorOptions {
+    /// Keep per-residue values for the residue CSV
+    pub per_residue: bool,
+    /// Add the gyration tensor shape columns
+    pub shape: bool,
+    pub hull_atoms: HullAtoms,
+    /// Add the molecular volume and packing density columns
+    pub packing: bool,
+    /// Contact definition of the relative and absolute contact order columns
+    pub contact_order: ContactOrderOptions,
+    /// Residue distance matrix or contact map exported for every model
+    pub matrix: Option<MatrixOptions>,
+    /// Add the pLDDT segmentation columns
+    pub confidence: bool,
+    /// Thresholds and smoothing of the pLDDT segmentation and of the smoothed residue pLDDT
+    pub segmentation: SegmentationOptions,
+    /// Meaning of the B-factor column and thresholds of the confidence fraction columns
+    pub confidence_thresholds: ConfidenceThresholds,
+    /// Parse structural domains and add the domain columns
+    pub domains: Option<DomainOptions>,
+    /// Add a row for every chain after the model row
+    pub per_chain: bool,
+    /// Add a row for every domain after the model row; needs [`DescriptorOptions::domains`]
+    pub per_domain: bool,
+}
+
+/// Per-residue values, written to the residue CSV.
+pub struct ResidueDescriptors {
+    pub chain_id: String,
+    pub residue_seq: i32,
+    pub insertion_code: Option<char>,
+    pub residue_name: String,
+    pub phi: Option<f32>,
+    pub psi: Option<f32>,
+    pub omega: Option<f32>,
+    pub ramachandran: Option<RamachandranRegion>,
+    /// chi1 to chi4
+    pub chi: [Option<f32>; 4],
+    pub rotamer: Option<RotamerFit>,
+    /// CA B-factor, `None` without CA
+    pub plddt: Option<f32>,
+    pub smoothed_plddt: Option<f32>,
+}
+
+/// pLDDT segmentation of a model, over all its chains.
+pub struct ConfidenceDescriptors {
+    pub low_segments: usize,
+    pub low_residues: usize,
+    pub disordered_n_terminus: usize,
+    pub disordered_c_terminus: usize,
+    /// Longest high-confidence stretch of any chain
+    pub longest_high_confidence: usize,
+}
+
+/// Structural domains of a model.
+pub struct DomainDescriptors {
+    pub count: usize,
+    /// Residues with a CA atom outside every domain
+    pub unassigned: usize,
+    /// Residue ranges of every domain, e.g. `A1-120,A250-300|A121-249`
+    pub ranges: String,
+}
+
+/// Descriptors computed for a single model of a structure file, or for one chain or domain of it.
+pub struct ModelDescriptors {
+    pub id: String,
+    pub model: i32,
+    /// `model`, or `chain_A` and `domain_1` for the rows of [`DescriptorOptions::per_chain`] and [`DescriptorOptions::per_domain`]
+    pub part: String,
+    pub rg: f32,
+    pub vol: f32,
+    pub co: f32,
+    /// `None` when the model is too short for the contact order options
+    pub relative_co: Option<f32>,
+    pub absolute_co: Option<f32>,
+    /// Mean CA B-factor and fractions past [`DescriptorOptions::confidence_thresholds`]
+    pub confidence_statistics: ConfidenceStatistics,
+    pub length: usize,
+    pub chain_breaks: usize,
+    pub longest_segment: usize,
+    pub helix_fraction: f32,
+    pub strand_fraction: f32,
+    pub coil_fraction: f32,
+    /// DSSP state of every residue with a complete backbone
+    pub dssp: String,
+    pub rama_favoured: f32,
+    pub rama_allowed: f32,
+    pub rama_outlier: f32,
+    /// Fraction of complete side chains off every rotamer of the built-in library
+    pub rotamer_outlier: f32,
+    /// Volume of the bounding box aligned on the principal axes
+    pub oriented_box_volume: f32,
+    pub hull_volume: f32,
+    pub hull_area: f32,
+    /// Gyration tensor of the CA atoms, `None` unless [`DescriptorOptions::shape`] is set
+    pub shape: Option<ShapeDescriptors>,
+    /// Volumes of the heavy atoms, `None` unless [`DescriptorOptions::packing`] is set
+    pub volume: Option<MolecularVolume>,
+    /// `None` unless [`DescriptorOptions::confidence`] is set
+    pub confidence: Option<ConfidenceDescriptors>,
+    /// `None` without a companion PAE file
+    pub pae: Option<PaeSummary>,
+    /// `None` unless [`DescriptorOptions::domains`] is set
+    pub domains: Option<DomainDescriptors>,
+    /// `None` unless [`DescriptorOptions::matrix`] is set, taken by [`write_model_matrices`]
+    pub matrix: Option<ModelMatrix>,
+    /// Empty unless [`DescriptorOptions::per_residue`] is set
+    pub residues: Vec<ResidueDescriptors>,
+}
+
+/// PAE files of a directory listing, by the entry ID of the structure they belong to.
+pub fn pae_files(paths: &[String]) -> HashMap<String, String> {
+    paths
+        .iter()
+        .filter(|path| is_pae_file(path))
+        .filter_map(|path| Some((pae_entry_id(path)?, path.clone())))
+        .collect()
+}
+
+pub fn process_pdb_file(
+    file_path: &str,
+    options: &ParseOptions,
+    descriptors: &DescriptorOptions,
+    pae_files: &HashMap<String, String>,
+    matrices: Option<&MatrixWriter>,
+) -> Vec<ModelDescriptors> {
+    // Parse the PDB or mmCIF file; unreadable files are reported and skipped.
+    let pdb = match parse_structure_with_options(file_path, options) {
+        Ok(pdb) => pdb,
+        Err(e) => {
+            eprintln!("{}: skipped structure. {}", file_path, e);
+            return Vec::new();
+        }
+    };
+    report_warnings(file_path, &pdb);
 
+    // Extract the file stem for naming purposes, without `.pdb.gz`-like compound extensions.
+    let file_stem = entry_id(file_path).unwrap_or_else(|| "unknown".to_string());
 
-pub fn process_pdb_file(file_path: &str) -> (String, f32, f32, f32, f32, f32, f32, f32, usize) {
-    // Parse the PDB file.
-    let pdb = parse_pdb(file_path).expect("Failed to parse PDB file");
+    let pae = pae_files.get(&file_stem).and_then(|path| {
+        read_pae(path).map_err(|e| eprintln!("{}: skipped PAE. {}", path, e)).ok()
+    });
+    let mut results = describe_structure(&file_stem, &pdb, descriptors, pae.as_ref());
+    write_model_matrices(&mut results, matrices);
+    results
+}
+
+/// Writes the residue matrices of described models and drops them, so they are not kept until the run ends.
+pub fn write_model_matrices(results: &mut [ModelDescriptors], matrices: Option<&MatrixWriter>) {
+    let Some(writer) = matrices else { return };
+    for d in results {
+        if let Some(matrix) = d.matrix.take() {
+            let name = format!("{}_{}", d.id, d.model);
+            writer.write(&name, &matrix).expect("Failed to write residue matrix");
+        }
+    }
+}
+
+/// Prints the records skipped in lenient mode.
+pub fn report_warnings(source: &str, pdb: &ParsedPDB) {
+    for warning in &pdb.warnings {
+        eprintln!("{}: skipped record. {}", source, warning);
+    }
+}
+
+/// Residue ranges of every domain, `|` between domains and `,` between the ranges of one domain.
+fn domain_ranges(domains: &Domains) -> String {
+    let label = |residue: &ResidueView| {
+        format!("{}{}{}", residue.chain_id(), residue.residue_seq(), optional(residue.insertion_code()))
+    };
+    domains
+        .domains
+        .iter()
+        .map(|domain| {
+            domain
+                .ranges
+                .iter()
+                .map(|range| format!("{}-{}", label(&domains.residues[range.start]), label(&domains.residues[range.end - 1])))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// A model holding only the given residues, in chains of consecutive residues with the same chain ID.
+fn sub_model<'a>(serial: i32, residues: impl IntoIterator<Item = ResidueView<'a>>) -> Model {
+    let mut chains: Vec<Chain> = Vec::new();
+    for residue in residues {
+        match chains.last_mut() {
+            Some(chain) if chain.id == residue.chain_id() => chain.atoms.extend_from_slice(residue.atoms),
+            _ => chains.push(Chain { id: residue.chain_id().to_string(), atoms: residue.atoms.to_vec() }),
+        }
+    }
+    Model { serial, chains }
+}
+
+pub fn describe_structure(
+    id: &str,
+    pdb: &ParsedPDB,
+    options: &DescriptorOptions,
+    pae: Option<&PredictedAlignedError>,
+) -> Vec<ModelDescriptors> {
+    // Chains and domains get every model descriptor, but no residue values or matrices.
+    let part_options = DescriptorOptions { per_residue: false, matrix: None, per_chain: false, per_domain: false, ..options.clone() };
+    let describe_part = |part: String, model: &Model, pae: Option<&PredictedAlignedError>| {
+        let domains = part_options.domains.as_ref().map(|domains| parse_domains(&model.view(), domains, pae));
+        describe_model(id, part, model, &part_options, pae, domains.as_ref())
+    };
+
+    // One row per model, so NMR ensembles are not merged into a single chain.
+    pdb.models
+        .iter()
+        .flat_map(|model| {
+            let view = model.view();
+            // PAE rows follow the residues with a CA atom of every chain.
+            let residues = view.ca_atoms().len();
+            let pae = pae.filter(|pae| {
+                if pae.size != residues {
+                    eprintln!("{} model {}: skipped PAE. {} rows for {} residues", id, model.serial, pae.size, residues);
+                }
+                pae.size == residues
+            });
+            let pae_of = |indices: Vec<usize>| pae.map(|pae| pae.submatrix(&indices));
+
+            let domains = options.domains.as_ref().map(|domains| parse_domains(&view, domains, pae));
+            let mut rows = vec![describe_model(id, "model".to_string(), model, options, pae, domains.as_ref())];
+            if options.per_chain {
+                let mut offset = 0;
+                for chain in &view.chains {
+                    let length = chain.ca_atoms().len();
+                    if length > 0 {
+                        let part = sub_model(model.serial, chain.residues.iter().copied());
+                        let pae = pae_of((offset..offset + length).collect());
+                        rows.push(describe_part(format!("chain_{}", chain.id()), &part, pae.as_ref()));
+                    }
+                    offset += length;
+                }
+            }
+            if let (true, Some(domains)) = (options.per_domain, &domains) {
+                for (d, domain) in domains.domains.iter().enumerate() {
+                    let part = sub_model(model.serial, domain.indices().map(|i| domains.residues[i]));
+                    let pae = pae_of(domain.indices().collect());
+                    rows.push(describe_part(format!("domain_{}", d + 1), &part, pae.as_ref()));
+                }
+            }
+            rows
+        })
+        .collect()
+}
 
-    let ca_atoms = get_ca_atoms(&pdb);
+/// Descriptors of one model, or of the part of a model built by [`sub_model`].
+fn describe_model(
+    id: &str,
+    part: String,
+    model: &Model,
+    options: &DescriptorOptions,
+    pae: Option<&PredictedAlignedError>,
+    domains: Option<&Domains>,
+) -> ModelDescriptors {
+    let ca_atoms = get_ca_atoms(model);
 
     let rg = radius_of_gyration(&ca_atoms);
     let vol = bounding_box_volume(&ca_atoms);
     let co = contact_order(&ca_atoms);
-    let (mean_plddt, plddt_50, plddt_70, plddt_90) = plddt_statistics(&ca_atoms);
-
-    // Extract the file stem for naming purposes.
-    let file_stem = Path::new(file_path)
-        .file_stem()
-        .and_then(|stem| stem.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+    let confidence_statistics = confidence_statistics(&ca_atoms, &options.confidence_thresholds);
 
     let length = ca_atoms.len();
+    let view = model.view();
+    let breaks = model_chain_breaks(&view);
+    let secondary_structure = dssp(&view);
+    let fractions = secondary_structure.fractions();
+    let dihedrals = backbone_dihedrals(&view);
+    let rama = ramachandran_summary(&dihedrals);
+    let rotamers = rotamer_summary(&side_chain_dihedrals(&view));
+    let shape = options
+        .shape
+        .then(|| gyration_tensor(&ca_atoms).map_or_else(ShapeDescriptors::default, |tensor| tensor.shape()));
+    let size_atoms = match options.hull_atoms {
+        HullAtoms::Ca => ca_atoms.clone(),
+        HullAtoms::Heavy => get_heavy_atoms(model),
+    };
+    let oriented_box_volume = oriented_bounding_box(&size_atoms).map_or(0.0, |b| b.volume());
+    let hull = convex_hull(&size_atoms);
+    let configured_co = contact_order_with(&view, &options.contact_order);
+    let volume = options.packing.then(|| molecular_volume(&get_heavy_atoms(model), &VolumeOptions::default()));
+    let matrix = options.matrix.as_ref().map(|matrix| model_matrix(&view, matrix));
+    let chains = if options.confidence || options.per_residue {
+        chain_confidence(&view, &options.segmentation)
+    } else {
+        Vec::new()
+    };
+    let confidence = options.confidence.then(|| ConfidenceDescriptors {
+        low_segments: chains.iter().map(|c| c.profile.low_segments.len()).sum(),
+        low_residues: chains.iter().map(|c| c.profile.low_residues()).sum(),
+        disordered_n_terminus: chains.iter().map(|c| c.profile.disordered_n_terminus).sum(),
+        disordered_c_terminus: chains.iter().map(|c| c.profile.disordered_c_terminus).sum(),
+        longest_high_confidence: chains.iter().map(|c| c.profile.longest_high_confidence.len()).max().unwrap_or(0),
+    });
+    // The intra- and inter-domain fields stay empty unless domains are parsed.
+    let pae = pae.map(|pae| match domains {
+        Some(domains) => pae_domain_summary(pae, &domains.domains),
+        None => pae_summary(pae, &[]),
+    });
+    let domains = domains.map(|domains| DomainDescriptors {
+        count: domains.domains.len(),
+        unassigned: domains.unassigned(),
+        ranges: domain_ranges(domains),
+    });
+
+    let residues = if options.per_residue {
+        let smoothed: HashMap<_, f32> = chains
+            .iter()
+            .flat_map(|c| c.residues.iter().zip(&c.profile.smoothed))
+            .map(|(r, &value)| ((r.chain_id(), r.residue_seq(), r.insertion_code()), value))
+            .collect();
+        dihedrals
+            .iter()
+            .map(|d| {
+                let side_chain = SideChainDihedrals::new(d.residue);
+                ResidueDescriptors {
+                    chain_id: d.residue.chain_id().to_string(),
+                    residue_seq: d.residue.residue_seq(),
+                    insertion_code: d.residue.insertion_code(),
+                    residue_name: d.residue.name().to_string(),
+                    phi: d.phi,
+                    psi: d.psi,
+                    omega: d.omega,
+                    ramachandran: d.ramachandran(),
+                    chi: side_chain.chi,
+                    rotamer: side_chain.rotamer(),
+                    plddt: d.residue.ca().map(|ca| ca.bfactor),
+                    smoothed_plddt: smoothed
+                        .get(&(d.residue.chain_id(), d.residue.residue_seq(), d.residue.insertion_code()))
+                        .copied(),
+                }
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
 
-    (file_stem, rg, vol, co, mean_plddt, plddt_50, plddt_70, plddt_90, length)
+    ModelDescriptors {
+        id: id.to_string(),
+        model: model.serial,
+        part,
+        rg,
+        vol,
+        co,
+        relative_co: configured_co.map(|c| c.relative),
+        absolute_co: configured_co.map(|c| c.absolute),
+        confidence_statistics,
+        length,
+        chain_breaks: breaks.breaks.len(),
+        longest_segment: breaks.longest_segment(),
+        helix_fraction: fractions.helix,
+        strand_fraction: fractions.strand,
+        coil_fraction: fractions.coil,
+        dssp: secondary_structure.string(),
+        rama_favoured: rama.favoured,
+        rama_allowed: rama.allowed,
+        rama_outlier: rama.outlier,
+        rotamer_outlier: rotamers.outlier,
+        oriented_box_volume,
+        hull_volume: hull.volume,
+        hull_area: hull.area,
+        shape,
+        volume,
+        confidence,
+        pae,
+        domains,
+        matrix,
+        residues,
+    }
 }
 
 fn append_to_file(file_path: &str, output: &str) {
@@ -46,6 +421,57 @@ fn append_to_file(file_path: &str, output: &str) {
     writeln!(file, "{}", output).expect("Failed to write to file");
 }
 
+/// Formats an optional value, leaving the CSV field empty when it is missing.
+fn optional<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or_else(String::new, |v| v.to_string())
+}
+
+/// Mean and threshold fraction columns of the confidence source, e.g. `mean_pLDDT;Fraction_pLDDT_Over_50`.
+fn confidence_columns(thresholds: &ConfidenceThresholds) -> String {
+    let (name, side) = match thresholds.source {
+        ConfidenceSource::Plddt => ("pLDDT", "Over"),
+        ConfidenceSource::BFactor => ("BFactor", "Under"),
+    };
+    std::iter::once(format!("mean_{}", name))
+        .chain(thresholds.values.iter().map(|t| format!("Fraction_{}_{}_{}", name, side, t)))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn confidence_values(statistics: &ConfidenceStatistics) -> String {
+    std::iter::once(statistics.mean)
+        .chain(statistics.fractions.iter().map(|f| f.fraction))
+        .map(|v| format!("{:.4}", v))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn residue_rows(d: &ModelDescriptors) -> impl Iterator<Item = String> + '_ {
+    d.residues.iter().map(move |r| {
+        let region = r.ramachandran.map(|region| match region {
+            RamachandranRegion::Favoured => "Favoured",
+            RamachandranRegion::Allowed => "Allowed",
+            RamachandranRegion::Outlier => "Outlier",
+        });
+        let rotamer = r.rotamer.as_ref().map(|fit| match fit {
+            RotamerFit::Rotamer(name) => name.as_str(),
+            RotamerFit::Outlier => "Outlier",
+        });
+        format!(
+            "{};{};{};{}{};{};{};{};{};{};{};{};{};{}",
+            d.id, d.model, r.chain_id, r.residue_seq, optional(r.insertion_code), r.residue_name,
+            optional(r.phi.map(|a| format!("{:.2}", a))),
+            optional(r.psi.map(|a| format!("{:.2}", a))),
+            optional(r.omega.map(|a| format!("{:.2}", a))),
+            optional(region),
+            r.chi.iter().map(|chi| optional(chi.map(|a| format!("{:.2}", a)))).collect::<Vec<_>>().join(";"),
+            optional(rotamer),
+            optional(r.plddt.map(|p| format!("{:.2}", p))),
+            optional(r.smoothed_plddt.map(|p| format!("{:.2}", p)))
+        )
+    })
+}
+
 fn main() {
 
     let config = parse_arguments();
@@ -55,31 +481,54 @@ fn main() {
         config.num_cpus, config.pdb_dir, config.output_file
     );
 
-    fs::write(&config.output_file, "ID;Gyration_Radius;Box_Volume;Contact_Order;mean_pLDDT;pLDDT_50;pLDDT_70;pLDDT_90;seq_len\n").expect("Failed to write CSV header");
+    let mut header = format!("ID;Model;Gyration_Radius;Box_Volume;Contact_Order;{};seq_len;", confidence_columns(&config.confidence_thresholds));
+    header += "Chain_Breaks;Longest_Segment;Helix_Fraction;Strand_Fraction;Coil_Fraction;DSSP;Rama_Favoured;Rama_Allowed;Rama_Outlier;Rotamer_Outlier;OBB_Volume;Hull_Volume;Hull_Area;Relative_CO;Absolute_CO;mean_PAE;Fraction_PAE_Under_5;Intra_Domain_PAE;Inter_Domain_PAE";
+    if config.shape {
+        header += ";Gyration_Eigenvalue_1;Gyration_Eigenvalue_2;Gyration_Eigenvalue_3;Asphericity;Acylindricity;Shape_Anisotropy";
+    }
+    if config.packing {
+        header += ";VdW_Volume;Molecular_Volume;Packing_Density";
+    }
+    if config.confidence {
+        header += ";Low_pLDDT_Segments;Low_pLDDT_Residues;Disordered_N_Terminus;Disordered_C_Terminus;Longest_Confident_Stretch";
+    }
+    if config.domains.is_some() {
+        header += ";Domains;Unassigned_Residues;Domain_Ranges";
+    }
+    if config.per_chain || config.per_domain {
+        header += ";Part";
+    }
+    fs::write(&config.output_file, header + "\n").expect("Failed to write CSV header");
 
     let pool = ThreadPoolBuilder::new()
         .num_threads(config.num_cpus)
         .build()
         .expect("Failed to create thread pool");
 
-    let pdb_files: Vec<String> = fs::read_dir(&config.pdb_dir)
-        .expect("Failed to read PDB directory")
-        .filter_map(|entry| {
-            let path = entry.ok()?.path();
-            if path.extension()?.to_str()? == "pdb" {
-                Some(path.to_str()?.to_string())
-            } else {
-                None
-            }
-        })
-        .collect();
+    // The input is either a single tar shard or a directory of structure files and shards.
+    let (pdb_files, tar_shards, pae_files): (Vec<String>, Vec<String>, _) = if is_tar_shard(&config.pdb_dir) {
+        (Vec::new(), vec![config.pdb_dir.clone()], HashMap::new())
+    } else {
+        let paths: Vec<_> = fs::read_dir(&config.pdb_dir)
+            .expect("Failed to read PDB directory")
+            .filter_map(|entry| entry.ok()?.path().to_str().map(str::to_string))
+            .collect();
+        let wanted_format = |path: &String| match StructureFormat::from_extension(path) {
+            Some(f) => config.format.is_none_or(|wanted| wanted == f),
+            None => false,
+        };
+        let pdb_files = paths.iter().filter(|p| wanted_format(p)).cloned().collect();
+        let tar_shards = paths.iter().filter(|p| is_tar_shard(Path::new(p))).cloned().collect();
+        (pdb_files, tar_shards, pae_files(&paths))
+    };
 
-    if pdb_files.is_empty() {
-        eprintln!("No PDB files found in directory: {}", config.pdb_dir);
+    if pdb_files.is_empty() && tar_shards.is_empty() {
+        eprintln!("No PDB or mmCIF files found in: {}", config.pdb_dir);
         return;
     };
 
-    let files_to_process = if let Some(n) = config.subset {
+    // A subset takes random files, then the first structures of the shards up to the same total.
+    let files_to_process: Vec<String> = if let Some(n) = config.subset {
         let mut _rng = rand::rng();
         pdb_files
             .choose_multiple(&mut _rng, n.min(pdb_files.len()))
@@ -89,17 +538,89 @@ fn main() {
         pdb_files
     };
         
+    let options = ParseOptions {
+        lenient: config.lenient,
+        ..Default::default()
+    };
+    let descriptor_options = DescriptorOptions {
+        per_residue: config.residue_output.is_some(),
+        shape: config.shape,
+        hull_atoms: config.hull_atoms,
+        packing: config.packing,
+        contact_order: config.contact_order,
+        matrix: config.matrix.clone(),
+        confidence: config.confidence,
+        segmentation: config.segmentation,
+        confidence_thresholds: config.confidence_thresholds.clone(),
+        domains: config.domains,
+        per_chain: config.per_chain,
+        per_domain: config.per_domain,
+    };
+
+    let matrices = config.matrix.as_ref().map(|matrix| MatrixWriter::create(&matrix.output).expect("Failed to create residue matrix output"));
     let results: Vec<_> = pool.install(|| {
-        files_to_process
+        let mut results: Vec<_> = files_to_process
             .par_iter()
-            .map(|file| process_pdb_file(file))
-            .collect()
+            .flat_map(|file| process_pdb_file(file, &options, &descriptor_options, &pae_files, matrices.as_ref()))
+            .collect();
+
+        // Shards are streamed one after the other, their members are parsed in parallel.
+        let mut remaining = config.subset.map(|n| n - files_to_process.len());
+        for shard in &tar_shards {
+            if remaining == Some(0) {
+                break;
+            }
+            let (shard_results, structures) = process_tar_shard(shard, config.format, remaining, &options, &descriptor_options, matrices.as_ref());
+            results.extend(shard_results);
+            remaining = remaining.map(|n| n - structures);
+        }
+        results
     });
         
     let output: String = results
         .iter()
-        .map(|(id, rg, vol, co, pmean, p50, p70, p90, len)| {
-            format!("{};{:.4};{:.4};{:.4};{:.4};{:.4};{:.4};{:.4};{}", id, rg, vol, co, pmean, p50, p70, p90, len)
+        .map(|d| {
+            let mut row = format!(
+                "{};{};{:.4};{:.4};{:.4};{};{};{};{};{:.4};{:.4};{:.4};{};{:.4};{:.4};{:.4};{:.4};{:.4};{:.4};{:.4};{};{}",
+                d.id, d.model, d.rg, d.vol, d.co, confidence_values(&d.confidence_statistics), d.length,
+                d.chain_breaks, d.longest_segment, d.helix_fraction, d.strand_fraction, d.coil_fraction, d.dssp,
+                d.rama_favoured, d.rama_allowed, d.rama_outlier, d.rotamer_outlier,
+                d.oriented_box_volume, d.hull_volume, d.hull_area,
+                optional(d.relative_co.map(|co| format!("{:.4}", co))), optional(d.absolute_co.map(|co| format!("{:.4}", co)))
+            );
+            // Empty PAE fields without a companion file
+            let pae = [
+                d.pae.map(|p| p.mean),
+                d.pae.map(|p| p.fraction_confident),
+                d.pae.and_then(|p| p.intra_domain),
+                d.pae.and_then(|p| p.inter_domain),
+            ];
+            for value in pae {
+                row += &format!(";{}", optional(value.map(|v| format!("{:.4}", v))));
+            }
+            if let Some(shape) = &d.shape {
+                let [l1, l2, l3] = shape.eigenvalues;
+                row += &format!(
+                    ";{:.4};{:.4};{:.4};{:.4};{:.4};{:.4}",
+                    l1, l2, l3, shape.asphericity, shape.acylindricity, shape.anisotropy
+                );
+            }
+            if let Some(volume) = &d.volume {
+                row += &format!(";{:.4};{:.4};{:.4}", volume.van_der_waals, volume.molecular, volume.packing_density());
+            }
+            if let Some(c) = &d.confidence {
+                row += &format!(
+                    ";{};{};{};{};{}",
+                    c.low_segments, c.low_residues, c.disordered_n_terminus, c.disordered_c_terminus, c.longest_high_confidence
+                );
+            }
+            if let Some(domains) = &d.domains {
+                row += &format!(";{};{};{}", domains.count, domains.unassigned, domains.ranges);
+            }
+            if config.per_chain || config.per_domain {
+                row += &format!(";{}", d.part);
+            }
+            row
         })
         .collect::<Vec<String>>() 
         .join("\n"); 
@@ -107,5 +628,16 @@ fn main() {
     
     append_to_file(&config.output_file, &output);
 
+    if let Some(residue_file) = &config.residue_output {
+        fs::write(residue_file, "ID;Model;Chain;Residue;Residue_Name;Phi;Psi;Omega;Ramachandran;Chi1;Chi2;Chi3;Chi4;Rotamer;pLDDT;Smoothed_pLDDT\n")
+            .expect("Failed to write residue CSV header");
+        let rows: Vec<String> = results.iter().flat_map(residue_rows).collect();
+        append_to_file(residue_file, &rows.join("\n"));
+    }
+
+    if let Some(matrices) = matrices {
+        matrices.finish().expect("Failed to write residue matrices");
+    }
+
     println!("Processing complete. Results saved to {}", &config.output_file);
 }
\ No newline at end of file
@@ -0,0 +1,232 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use half::f16;
+use metrics::{contact_map, distance_matrix, ContactAtoms, MatrixLayout, ResidueMatrix};
+use npyz::npz::NpzWriter;
+use npyz::zip::write::FileOptions;
+use npyz::WriterBuilder;
+use pdb_io::ModelView;
+
+/// Residue matrix written for every model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixKind {
+    /// Shortest distance between the selected atoms of two residues, in A
+    Distances,
+    /// 1 where the distance is at most [`MatrixOptions::cutoff`], 0 otherwise
+    ContactMap,
+}
+
+/// Where the matrices go.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatrixOutput {
+    /// One `{id}_{model}.npy` file per model in this directory
+    PerStructure(PathBuf),
+    /// A single `.npz` archive holding one `{id}_{model}` array per model
+    Sharded(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatrixOptions {
+    pub kind: MatrixKind,
+    pub atoms: ContactAtoms,
+    pub layout: MatrixLayout,
+    /// Contact map cutoff, in A
+    pub cutoff: f32,
+    /// Store distances in half precision
+    pub half: bool,
+    pub output: MatrixOutput,
+}
+
+/// Matrix values with the element type they are written with.
+pub enum MatrixValues {
+    F32(Vec<f32>),
+    F16(Vec<f16>),
+    U8(Vec<u8>),
+}
+
+/// Array of one model, ready to be written.
+pub struct ModelMatrix {
+    pub shape: Vec<u64>,
+    pub values: MatrixValues,
+}
+
+fn shape_of<T: Copy>(matrix: &ResidueMatrix<T>) -> Vec<u64> {
+    matrix.shape().into_iter().map(|n| n as u64).collect()
+}
+
+/// Distance matrix or contact map of a model's polymer residues.
+pub fn model_matrix(model: &ModelView, options: &MatrixOptions) -> ModelMatrix {
+    if options.kind == MatrixKind::ContactMap {
+        let contacts = contact_map(model, options.atoms, options.cutoff, options.layout).contacts;
+        return ModelMatrix { shape: shape_of(&contacts), values: MatrixValues::U8(contacts.values) };
+    }
+    let distances = distance_matrix(model, options.atoms, options.layout).distances;
+    let shape = shape_of(&distances);
+    let values = if options.half {
+        MatrixValues::F16(distances.to_f16().values)
+    } else {
+        MatrixValues::F32(distances.values)
+    };
+    ModelMatrix { shape, values }
+}
+
+fn write_npy<T: npyz::Serialize + npyz::AutoSerialize + Copy>(path: &Path, shape: &[u64], values: &[T]) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut writer = npyz::WriteOptions::new().default_dtype().shape(shape).writer(file).begin_nd()?;
+    writer.extend(values.iter().copied())?;
+    writer.finish()
+}
+
+fn write_npz_array<T: npyz::Serialize + npyz::AutoSerialize + Copy>(
+    npz: &mut NpzWriter<BufWriter<File>>,
+    name: &str,
+    shape: &[u64],
+    values: &[T],
+) -> io::Result<()> {
+    let mut writer = npz.array::<T>(name, FileOptions::default())?.default_dtype().shape(shape).begin_nd()?;
+    writer.extend(values.iter().copied())?;
+    writer.finish()
+}
+
+/// Writes matrices as soon as their model is described, from any thread.
+pub enum MatrixWriter {
+    /// One `.npy` file per matrix in this directory
+    Directory(PathBuf),
+    /// Entries of an `.npz` archive, finished by [`MatrixWriter::finish`]
+    Archive(Mutex<NpzWriter<BufWriter<File>>>),
+}
+
+impl MatrixWriter {
+    /// Creates the output directory or the `.npz` archive.
+    pub fn create(output: &MatrixOutput) -> io::Result<Self> {
+        match output {
+            MatrixOutput::PerStructure(directory) => {
+                fs::create_dir_all(directory)?;
+                Ok(MatrixWriter::Directory(directory.clone()))
+            }
+            MatrixOutput::Sharded(path) => Ok(MatrixWriter::Archive(Mutex::new(NpzWriter::create(path)?))),
+        }
+    }
+
+    /// Writes one matrix, named by entry ID and model serial.
+    pub fn write(&self, name: &str, matrix: &ModelMatrix) -> io::Result<()> {
+        match self {
+            MatrixWriter::Directory(directory) => {
+                let path = directory.join(format!("{}.npy", name));
+                match &matrix.values {
+                    MatrixValues::F32(values) => write_npy(&path, &matrix.shape, values),
+                    MatrixValues::F16(values) => write_npy(&path, &matrix.shape, values),
+                    MatrixValues::U8(values) => write_npy(&path, &matrix.shape, values),
+                }
+            }
+            MatrixWriter::Archive(npz) => {
+                let mut npz = npz.lock().unwrap();
+                match &matrix.values {
+                    MatrixValues::F32(values) => write_npz_array(&mut npz, name, &matrix.shape, values),
+                    MatrixValues::F16(values) => write_npz_array(&mut npz, name, &matrix.shape, values),
+                    MatrixValues::U8(values) => write_npz_array(&mut npz, name, &matrix.shape, values),
+                }
+            }
+        }
+    }
+
+    /// Writes the central directory of the `.npz` archive.
+    pub fn finish(self) -> io::Result<()> {
+        if let MatrixWriter::Archive(npz) = self {
+            npz.into_inner().unwrap().zip_writer().finish()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use npyz::npz::NpzArchive;
+    use npyz::{DType, NpyFile};
+    use pdb_io::{parse_structure_with_options, ParseOptions};
+
+    const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../pdb_io/tests/data/");
+
+    fn options(layout: MatrixLayout, output: MatrixOutput) -> MatrixOptions {
+        MatrixOptions { kind: MatrixKind::Distances, atoms: ContactAtoms::Ca, layout, cutoff: 8.0, half: false, output }
+    }
+
+    fn type_str<R: io::Read>(npy: &NpyFile<R>) -> String {
+        match npy.dtype() {
+            DType::Plain(ty) => ty.to_string(),
+            dtype => panic!("unexpected dtype {:?}", dtype),
+        }
+    }
+
+    #[test]
+    fn test_write_matrices() {
+        let pdb = parse_structure_with_options(format!("{}AF-TEST-F1-model_v4.pdb", DATA), &ParseOptions::default()).unwrap();
+        let model = pdb.models[0].view();
+        let n = model.ca_atoms().len();
+        let full = model_matrix(&model, &options(MatrixLayout::Full, MatrixOutput::PerStructure(PathBuf::new())));
+        let condensed = model_matrix(&model, &options(MatrixLayout::Condensed, MatrixOutput::PerStructure(PathBuf::new())));
+        let MatrixValues::F32(full_values) = &full.values else { panic!("distances are f32") };
+        let MatrixValues::F32(condensed_values) = &condensed.values else { panic!("distances are f32") };
+        let contacts = contact_map(&model, ContactAtoms::Ca, 8.0, MatrixLayout::Full).contacts;
+        let contacts = ModelMatrix { shape: shape_of(&contacts), values: MatrixValues::U8(contacts.values) };
+        let half = ModelMatrix {
+            shape: full.shape.clone(),
+            values: MatrixValues::F16(full_values.iter().map(|&v| f16::from_f32(v)).collect()),
+        };
+
+        // The condensed layout is the upper triangle of the full one, row after row.
+        let upper: Vec<f32> = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j))).map(|(i, j)| full_values[i * n + j]).collect();
+        assert_eq!(full.shape, [n as u64, n as u64]);
+        assert_eq!(condensed.shape, [(n * (n - 1) / 2) as u64]);
+        assert_eq!(&upper, condensed_values);
+
+        let directory = std::env::temp_dir().join(format!("pdb_descriptors_matrices_{}", std::process::id()));
+        let writer = MatrixWriter::create(&MatrixOutput::PerStructure(directory.clone())).unwrap();
+        writer.write("full", &full).unwrap();
+        writer.write("condensed", &condensed).unwrap();
+        writer.write("half", &half).unwrap();
+        writer.write("contacts", &contacts).unwrap();
+        writer.finish().unwrap();
+
+        let bytes = fs::read(directory.join("full.npy")).unwrap();
+        assert!(bytes.starts_with(b"\x93NUMPY"));
+        let npy = NpyFile::new(&bytes[..]).unwrap();
+        assert_eq!(npy.shape(), [n as u64, n as u64]);
+        assert_eq!(type_str(&npy), "<f4");
+        assert_eq!(&npy.into_vec::<f32>().unwrap(), full_values);
+
+        let npy = NpyFile::new(File::open(directory.join("condensed.npy")).unwrap()).unwrap();
+        assert_eq!(npy.shape(), [(n * (n - 1) / 2) as u64]);
+        assert_eq!(&npy.into_vec::<f32>().unwrap(), condensed_values);
+
+        let npy = NpyFile::new(File::open(directory.join("half.npy")).unwrap()).unwrap();
+        assert_eq!(type_str(&npy), "<f2");
+        assert_eq!(npy.into_vec::<f16>().unwrap()[1], f16::from_f32(full_values[1]));
+
+        let npy = NpyFile::new(File::open(directory.join("contacts.npy")).unwrap()).unwrap();
+        assert_eq!(type_str(&npy), "|u1");
+        assert_eq!(npy.into_vec::<u8>().unwrap().len(), n * n);
+
+        // The archive holds the same arrays, named without extension.
+        let path = directory.join("shard.npz");
+        let writer = MatrixWriter::create(&MatrixOutput::Sharded(path.clone())).unwrap();
+        writer.write("full", &full).unwrap();
+        writer.write("condensed", &condensed).unwrap();
+        writer.finish().unwrap();
+
+        let mut npz = NpzArchive::open(&path).unwrap();
+        let npy = npz.by_name("full").unwrap().expect("full array");
+        assert_eq!(npy.shape(), [n as u64, n as u64]);
+        assert_eq!(&npy.into_vec::<f32>().unwrap(), full_values);
+        let npy = npz.by_name("condensed").unwrap().expect("condensed array");
+        assert_eq!(npy.shape(), [(n * (n - 1) / 2) as u64]);
+        assert_eq!(&npy.into_vec::<f32>().unwrap(), condensed_values);
+        assert!(npz.by_name("half").unwrap().is_none());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use pdb_io::{
+    decompress_reader, entry_id, is_pae_file, pae_entry_id, parse_structure_from_reader, read_pae_from_reader,
+    strip_compression_extension, ParseOptions, StructureFormat,
+};
+use rayon::prelude::*;
+
+use crate::matrices::MatrixWriter;
+use crate::{describe_structure, report_warnings, write_model_matrices, DescriptorOptions, ModelDescriptors};
+
+/// Number of archive members held in memory before being parsed in parallel.
+const SHARD_BATCH_SIZE: usize = 4096;
+
+/// Whether the path looks like a tar archive, possibly compressed (`.tar`, `.tar.gz`, `.tgz`).
+pub fn is_tar_shard<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    let extension = |p: &Path| p.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    matches!(extension(path).as_deref(), Some("tgz"))
+        || matches!(extension(&strip_compression_extension(path)).as_deref(), Some("tar"))
+}
+
+/// Computes descriptors for the structure members of a tar shard without extracting it.
+///
+/// Reads at most `limit` structures and returns their descriptors with the number of
+/// structures read; see [`process_tar_reader`].
+pub fn process_tar_shard(
+    shard_path: &str,
+    format: Option<StructureFormat>,
+    limit: Option<usize>,
+    options: &ParseOptions,
+    descriptors: &DescriptorOptions,
+    matrices: Option<&MatrixWriter>,
+) -> (Vec<ModelDescriptors>, usize) {
+    let file = File::open(shard_path).expect("Failed to open tar shard");
+    let reader = decompress_reader(BufReader::new(file)).expect("Failed to read tar shard");
+    process_tar_reader(reader, format, limit, SHARD_BATCH_SIZE, options, descriptors, matrices)
+}
+
+/// Structure members read from a shard: entry ID, format and still compressed content.
+type Batch = Vec<(String, StructureFormat, Vec<u8>)>;
+
+/// PAE members waiting for their structure: content and number of batches parsed when read.
+type PendingPae = HashMap<String, (Vec<u8>, usize)>;
+
+/// Computes descriptors for the structure members of a tar stream, in archive order.
+///
+/// Members are read sequentially, then parsed in parallel by batches of `batch_size`.
+/// A batch is only parsed once the next structure shows up, so PAE files stored right
+/// after their model are found. PAE JSON members are kept, still compressed, until
+/// their structure is parsed or the batch after the one they were read in is done;
+/// further away from their structure they are reported and dropped.
+///
+/// Members that cannot be read or parsed are reported and skipped, as are other members
+/// and structures whose format differs from `format`. Stops at the structure after the
+/// first `limit` ones.
+pub fn process_tar_reader<R: Read>(
+    reader: R,
+    format: Option<StructureFormat>,
+    limit: Option<usize>,
+    batch_size: usize,
+    options: &ParseOptions,
+    descriptors: &DescriptorOptions,
+    matrices: Option<&MatrixWriter>,
+) -> (Vec<ModelDescriptors>, usize) {
+    let mut archive = tar::Archive::new(reader);
+
+    let mut results = Vec::new();
+    let mut batch: Batch = Vec::with_capacity(batch_size);
+    let mut paes: PendingPae = HashMap::new();
+    let (mut structures, mut batches) = (0, 0);
+
+    for entry in archive.entries().expect("Failed to list tar shard members") {
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("Skipped unreadable tar shard member. {}", e);
+                continue;
+            }
+        };
+        let Ok(member_path) = entry.path().map(|path| path.into_owned()) else {
+            eprintln!("Skipped tar shard member with an invalid path");
+            continue;
+        };
+        let source = member_path.display().to_string();
+
+        if is_pae_file(&member_path) {
+            if let Some(id) = pae_entry_id(&member_path) {
+                let mut bytes = Vec::with_capacity(entry.size() as usize);
+                match entry.read_to_end(&mut bytes) {
+                    Ok(_) => {
+                        paes.insert(id, (bytes, batches));
+                    }
+                    Err(e) => eprintln!("{}: skipped PAE. {}", source, e),
+                }
+            }
+            continue;
+        }
+        let member_format = match StructureFormat::from_extension(&member_path) {
+            Some(f) if format.is_none_or(|wanted| wanted == f) => f,
+            _ => continue,
+        };
+        // Stop at the structure after the last one, so its PAE is still read.
+        if limit.is_some_and(|limit| structures >= limit) {
+            break;
+        }
+        let id = entry_id(&member_path).unwrap_or_else(|| "unknown".to_string());
+
+        if batch.len() >= batch_size.max(1) {
+            results.extend(process_batch(&batch, &mut paes, batches, options, descriptors, matrices));
+            batch.clear();
+            batches += 1;
+        }
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        if let Err(e) = entry.read_to_end(&mut bytes) {
+            eprintln!("{}: skipped structure. {}", source, e);
+            continue;
+        }
+        batch.push((id, member_format, bytes));
+        structures += 1;
+    }
+    results.extend(process_batch(&batch, &mut paes, batches, options, descriptors, matrices));
+    for id in paes.keys() {
+        eprintln!("{}: skipped PAE without structure", id);
+    }
+
+    (results, structures)
+}
+
+/// Parses a batch of structures with their PAE and writes their matrices, then drops the PAE of the batch and
+/// the PAE read before the previous batch was parsed.
+fn process_batch(
+    batch: &Batch,
+    paes: &mut PendingPae,
+    batches: usize,
+    options: &ParseOptions,
+    descriptors: &DescriptorOptions,
+    matrices: Option<&MatrixWriter>,
+) -> Vec<ModelDescriptors> {
+    let results = batch
+        .par_iter()
+        .flat_map(|(id, format, bytes)| {
+            let pdb = match parse_structure_from_reader(&bytes[..], *format, options) {
+                Ok(pdb) => pdb,
+                Err(e) => {
+                    eprintln!("{}: skipped structure. {}", id, e);
+                    return Vec::new();
+                }
+            };
+            report_warnings(id, &pdb);
+            let pae = paes.get(id).and_then(|(bytes, _)| {
+                read_pae_from_reader(&bytes[..]).map_err(|e| eprintln!("{}: skipped PAE. {}", id, e)).ok()
+            });
+            let mut results = describe_structure(id, &pdb, descriptors, pae.as_ref());
+            write_model_matrices(&mut results, matrices);
+            results
+        })
+        .collect();
+    for (id, _, _) in batch {
+        paes.remove(id);
+    }
+    paes.retain(|id, (_, read_at)| {
+        let waiting = *read_at >= batches;
+        if !waiting {
+            eprintln!("{}: skipped PAE without structure", id);
+        }
+        waiting
+    });
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../pdb_io/tests/data/");
+
+    /// A tar archive built in memory from `(member path, content)` pairs.
+    fn tar_archive(members: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in members {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, &content[..]).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn fixture(name: &str) -> Vec<u8> {
+        std::fs::read(format!("{}{}", DATA, name)).unwrap()
+    }
+
+    #[test]
+    fn test_process_tar_reader() {
+        let broken = b"ATOM      1  CA  ALA A   1         abc   0.000   0.000  1.00 20.00           C\n".to_vec();
+        let archive = tar_archive(&[
+            ("shard/AF-TEST-F1-model_v4.pdb", fixture("AF-TEST-F1-model_v4.pdb")),
+            // Stored after its model, which sits in the previous batch of one
+            ("shard/AF-TEST-F1-predicted_aligned_error_v4.json", fixture("AF-TEST-F1-predicted_aligned_error_v4.json")),
+            ("shard/broken.pdb", broken),
+            ("shard/README", b"not a structure".to_vec()),
+            ("shard/seqres_gaps.pdb", fixture("seqres_gaps.pdb")),
+            ("shard/orphan_pae.json", b"{\"pae\": [[0.0]]}".to_vec()),
+        ]);
+        let (options, descriptors) = (ParseOptions::default(), DescriptorOptions::default());
+
+        // The malformed member is reported and skipped; the others are all described.
+        let (results, structures) = process_tar_reader(&archive[..], None, None, 1, &options, &descriptors, None);
+        assert_eq!(structures, 3);
+        let ids: Vec<&str> = results.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, ["AF-TEST-F1-model_v4", "seqres_gaps"]);
+        let pae = results[0].pae.expect("PAE stored after its model");
+        assert!((pae.mean - 8.0).abs() < 1e-4);
+        assert!(results[1].pae.is_none());
+
+        // A limit stops at the next structure, after the PAE of the last one.
+        let (results, structures) = process_tar_reader(&archive[..], None, Some(1), 1, &options, &descriptors, None);
+        assert_eq!((results.len(), structures), (1, 1));
+        assert!(results[0].pae.is_some());
+
+        let (results, _) = process_tar_reader(&archive[..], Some(StructureFormat::Mmcif), None, 1, &options, &descriptors, None);
+        assert!(results.is_empty());
+    }
+}
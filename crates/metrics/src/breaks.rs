@@ -0,0 +1,138 @@
+use std::ops::Range;
+
+use pdb_io::{AtomCoordinate, ChainView, ModelView, ResidueView};
+
+/// Longest C(i)-N(i+1) distance still counted as a peptide bond (1.33 A at equilibrium).
+pub const MAX_PEPTIDE_BOND: f32 = 2.0;
+/// Longest CA(i)-CA(i+1) distance between bonded residues, used when C or N is missing (3.8 A for trans peptides).
+pub const MAX_CA_CA_DISTANCE: f32 = 4.2;
+
+/// A discontinuity between two consecutive polymer residues of a chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainBreak<'a> {
+    pub chain_id: &'a str,
+    /// (residue number, insertion code) of the residues on each side of the break
+    pub before: (i32, Option<char>),
+    pub after: (i32, Option<char>),
+    /// C-N distance, or CA-CA distance for CA-only residues; `None` without either pair
+    pub distance: Option<f32>,
+    /// Whether the distance exceeds [`MAX_PEPTIDE_BOND`] (or [`MAX_CA_CA_DISTANCE`])
+    pub physical: bool,
+    /// Step of the residue numbering, e.g. 4 from residue 10 to 14, or -119 from 120 back to 1
+    pub numbering_step: i32,
+    /// Whether the numbering neither advances by one nor moves to a new insertion code
+    pub numbering: bool,
+}
+
+/// A run of polymer residues without any break.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment<'a> {
+    pub chain_id: &'a str,
+    /// Indices into the polymer residues of the chain
+    pub residues: Range<usize>,
+    pub first: (i32, Option<char>),
+    pub last: (i32, Option<char>),
+}
+
+impl Segment<'_> {
+    pub fn len(&self) -> usize {
+        self.residues.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.residues.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChainBreaks<'a> {
+    pub breaks: Vec<ChainBreak<'a>>,
+    pub segments: Vec<Segment<'a>>,
+}
+
+impl ChainBreaks<'_> {
+    /// Number of residues of the longest contiguous segment, 0 for a model without polymer.
+    pub fn longest_segment(&self) -> usize {
+        self.segments.iter().map(Segment::len).max().unwrap_or(0)
+    }
+}
+
+fn distance(a: &AtomCoordinate, b: &AtomCoordinate) -> f32 {
+    let (dx, dy, dz) = (a.x - b.x, a.y - b.y, a.z - b.z);
+    (dx * dx + dy * dy + dz * dz).sqrt()
+}
+
+fn residue_id(residue: &ResidueView) -> (i32, Option<char>) {
+    (residue.residue_seq(), residue.insertion_code())
+}
+
+/// Checks the link between two consecutive polymer residues.
+fn find_break<'a>(chain_id: &'a str, previous: &ResidueView<'a>, next: &ResidueView<'a>) -> Option<ChainBreak<'a>> {
+    let peptide = previous.atom("C").zip(next.atom("N")).map(|(c, n)| (distance(c, n), MAX_PEPTIDE_BOND));
+    let trace = previous.ca().zip(next.ca()).map(|(a, b)| (distance(a, b), MAX_CA_CA_DISTANCE));
+    let measured = peptide.or(trace);
+    let physical = measured.is_some_and(|(d, max)| d > max);
+
+    // Insertion codes (52, 52A, 53) keep or advance the number by one; any other step,
+    // backwards or repeating the same residue, is a numbering break.
+    let numbering_step = next.residue_seq() - previous.residue_seq();
+    let numbering = match numbering_step {
+        1 => false,
+        0 => next.insertion_code() == previous.insertion_code(),
+        _ => true,
+    };
+
+    (physical || numbering).then(|| ChainBreak {
+        chain_id,
+        before: residue_id(previous),
+        after: residue_id(next),
+        distance: measured.map(|(d, _)| d),
+        physical,
+        numbering_step,
+        numbering,
+    })
+}
+
+/// Finds chain breaks from C-N peptide bond lengths and jumps in residue numbering.
+///
+/// Consecutive polymer residues of a chain are split when their C-N distance
+/// exceeds [`MAX_PEPTIDE_BOND`] (CA-CA distance over [`MAX_CA_CA_DISTANCE`] when
+/// C or N is missing), or when residue numbers do not step by one, unless the
+/// number is kept with a new insertion code.
+pub fn chain_breaks<'a>(chain: &ChainView<'a>) -> ChainBreaks<'a> {
+    let residues: Vec<&ResidueView<'a>> = chain.polymer_residues().collect();
+    let chain_id = chain.id();
+
+    let mut result = ChainBreaks::default();
+    let mut start = 0;
+    for i in 0..residues.len() {
+        let next_break = residues.get(i + 1).and_then(|next| find_break(chain_id, residues[i], next));
+        let is_last = i + 1 == residues.len();
+
+        if let Some(chain_break) = next_break {
+            result.breaks.push(chain_break);
+        } else if !is_last {
+            continue;
+        }
+        result.segments.push(Segment {
+            chain_id,
+            residues: start..i + 1,
+            first: residue_id(residues[start]),
+            last: residue_id(residues[i]),
+        });
+        start = i + 1;
+    }
+
+    result
+}
+
+/// Chain breaks of every chain of a model.
+pub fn model_chain_breaks<'a>(model: &ModelView<'a>) -> ChainBreaks<'a> {
+    let mut result = ChainBreaks::default();
+    for chain in &model.chains {
+        let ChainBreaks { breaks, segments } = chain_breaks(chain);
+        result.breaks.extend(breaks);
+        result.segments.extend(segments);
+    }
+    result
+}
@@ -0,0 +1,210 @@
+use std::ops::Range;
+
+use pdb_io::{AtomCoordinate, ModelView, ResidueView};
+
+/// Thresholds and smoothing of [`confidence_profile`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SegmentationOptions {
+    /// Smoothed pLDDT below which a residue is low confidence (disordered in AlphaFold models)
+    pub low: f32,
+    /// Smoothed pLDDT from which a residue is high confidence
+    pub high: f32,
+    /// Residues of the centred moving average, truncated at the chain ends; 1 keeps the raw values.
+    /// A centred window is odd: an even value is widened by one, e.g. 4 averages 5 residues
+    pub window: usize,
+    /// Shortest run of low-confidence residues reported as a segment
+    pub min_segment: usize,
+}
+
+impl Default for SegmentationOptions {
+    fn default() -> Self {
+        SegmentationOptions { low: 50.0, high: 70.0, window: 5, min_segment: 5 }
+    }
+}
+
+/// Confidence segmentation of a run of residues.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConfidenceProfile {
+    /// Moving average of the pLDDT of every residue
+    pub smoothed: Vec<f32>,
+    /// Runs of at least [`SegmentationOptions::min_segment`] low-confidence residues, as indices
+    pub low_segments: Vec<Range<usize>>,
+    /// Low-confidence residues at the start, whatever their number
+    pub disordered_n_terminus: usize,
+    /// Low-confidence residues at the end, whatever their number; 0 when the whole run is low confidence
+    pub disordered_c_terminus: usize,
+    /// Longest run of high-confidence residues, empty without any
+    pub longest_high_confidence: Range<usize>,
+}
+
+impl ConfidenceProfile {
+    /// Residues inside the low-confidence segments.
+    pub fn low_residues(&self) -> usize {
+        self.low_segments.iter().map(Range::len).sum()
+    }
+}
+
+/// Maximal runs of consecutive indices where `keep` holds.
+fn runs(values: &[f32], keep: impl Fn(f32) -> bool) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (i, &value) in values.iter().enumerate() {
+        match (keep(value), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                runs.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        runs.push(s..values.len());
+    }
+    runs
+}
+
+/// Smooths and segments per-residue pLDDT values given in sequence order.
+///
+/// The moving average spans `window / 2` residues on each side, see [`SegmentationOptions::window`].
+pub fn confidence_profile(plddt: &[f32], options: &SegmentationOptions) -> ConfidenceProfile {
+    let half = options.window / 2;
+    let smoothed: Vec<f32> = (0..plddt.len())
+        .map(|i| {
+            let window = &plddt[i.saturating_sub(half)..(i + half + 1).min(plddt.len())];
+            window.iter().sum::<f32>() / window.len() as f32
+        })
+        .collect();
+
+    let low = runs(&smoothed, |v| v < options.low);
+    let disordered_n_terminus = low.first().filter(|r| r.start == 0).map_or(0, Range::len);
+    let disordered_c_terminus = low.last().filter(|r| r.end == smoothed.len() && r.start > 0).map_or(0, Range::len);
+    let longest_high_confidence = runs(&smoothed, |v| v >= options.high)
+        .into_iter()
+        .fold(0..0, |longest, run| if run.len() > longest.len() { run } else { longest });
+
+    ConfidenceProfile {
+        low_segments: low.into_iter().filter(|r| r.len() >= options.min_segment.max(1)).collect(),
+        smoothed,
+        disordered_n_terminus,
+        disordered_c_terminus,
+        longest_high_confidence,
+    }
+}
+
+/// Confidence profile of the polymer residues of a chain.
+#[derive(Debug, Clone)]
+pub struct ChainConfidence<'a> {
+    pub chain_id: &'a str,
+    /// Polymer residues with a CA atom, whose B-factor holds the pLDDT; profile indices point here
+    pub residues: Vec<ResidueView<'a>>,
+    pub profile: ConfidenceProfile,
+}
+
+/// Confidence profile of every chain of a model, from the pLDDT stored in the CA B-factors.
+///
+/// Meant for [`ConfidenceSource::Plddt`]: thresholds count low values as low confidence.
+///
+/// Chains are smoothed and segmented independently, so segments never span two chains.
+pub fn chain_confidence<'a>(model: &ModelView<'a>, options: &SegmentationOptions) -> Vec<ChainConfidence<'a>> {
+    model
+        .chains
+        .iter()
+        .map(|chain| {
+            let (residues, plddt): (Vec<ResidueView<'a>>, Vec<f32>) =
+                chain.polymer_residues().filter_map(|residue| Some((*residue, residue.ca()?.bfactor))).unzip();
+            ChainConfidence { chain_id: chain.id(), residues, profile: confidence_profile(&plddt, options) }
+        })
+        .collect()
+}
+
+/// What the B-factor column of a model holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfidenceSource {
+    /// Predicted LDDT from 0 to 100, as written by AlphaFold or ESMFold; higher is more confident
+    #[default]
+    Plddt,
+    /// Experimental atomic displacement, in A^2; lower is more confident
+    BFactor,
+}
+
+impl ConfidenceSource {
+    /// Whether `value` is strictly on the confident side of `threshold`.
+    pub fn passes(self, value: f32, threshold: f32) -> bool {
+        match self {
+            ConfidenceSource::Plddt => value > threshold,
+            ConfidenceSource::BFactor => value < threshold,
+        }
+    }
+
+    /// 50, 70 and 90 for pLDDT; 30, 50 and 80 A^2 for B-factors.
+    pub fn default_thresholds(self) -> Vec<f32> {
+        match self {
+            ConfidenceSource::Plddt => vec![50.0, 70.0, 90.0],
+            ConfidenceSource::BFactor => vec![30.0, 50.0, 80.0],
+        }
+    }
+}
+
+/// Thresholds of [`confidence_statistics`], in the unit of the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfidenceThresholds {
+    pub source: ConfidenceSource,
+    pub values: Vec<f32>,
+}
+
+impl ConfidenceThresholds {
+    /// The default thresholds of a source.
+    pub fn of(source: ConfidenceSource) -> Self {
+        ConfidenceThresholds { source, values: source.default_thresholds() }
+    }
+}
+
+impl Default for ConfidenceThresholds {
+    fn default() -> Self {
+        ConfidenceThresholds::of(ConfidenceSource::Plddt)
+    }
+}
+
+/// Share of residues past one threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThresholdFraction {
+    pub threshold: f32,
+    /// Between 0 and 1
+    pub fraction: f32,
+}
+
+impl ThresholdFraction {
+    /// The fraction as a percentage, between 0 and 100.
+    pub fn percent(&self) -> f32 {
+        self.fraction * 100.0
+    }
+}
+
+/// Mean and threshold fractions of the per-residue confidence.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConfidenceStatistics {
+    pub source: ConfidenceSource,
+    /// Mean CA B-factor, in pLDDT units or A^2 after `source`; 0 without residues
+    pub mean: f32,
+    pub residues: usize,
+    /// One entry per threshold, in the order given
+    pub fractions: Vec<ThresholdFraction>,
+}
+
+/// Mean confidence of CA atoms and the fraction of them on the confident side of each threshold.
+pub fn confidence_statistics(ca_atoms: &[&AtomCoordinate], thresholds: &ConfidenceThresholds) -> ConfidenceStatistics {
+    let n = ca_atoms.len();
+    let fraction = |threshold: f32| {
+        if n == 0 {
+            return 0.0;
+        }
+        ca_atoms.iter().filter(|ca| thresholds.source.passes(ca.bfactor, threshold)).count() as f32 / n as f32
+    };
+    ConfidenceStatistics {
+        source: thresholds.source,
+        mean: if n == 0 { 0.0 } else { ca_atoms.iter().map(|ca| ca.bfactor).sum::<f32>() / n as f32 },
+        residues: n,
+        fractions: thresholds.values.iter().map(|&threshold| ThresholdFraction { threshold, fraction: fraction(threshold) }).collect(),
+    }
+}
@@ -0,0 +1,187 @@
+use pdb_io::{AtomCoordinate, ModelView, ResidueView};
+
+use crate::neighbours::NeighbourSearch;
+
+/// Atoms standing for a residue when looking for contacts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactAtoms {
+    Ca,
+    /// CB, or CA for glycine and residues without CB
+    Cb,
+    /// Every non-hydrogen atom
+    HeavyAtoms,
+}
+
+impl ContactAtoms {
+    pub fn select<'a>(&self, residue: &ResidueView<'a>) -> Vec<&'a AtomCoordinate> {
+        match self {
+            ContactAtoms::Ca => residue.ca().into_iter().collect(),
+            ContactAtoms::Cb => residue.atom("CB").or_else(|| residue.ca()).into_iter().collect(),
+            ContactAtoms::HeavyAtoms => {
+                residue.atoms.iter().filter(|atom| atom.element != "H" && atom.element != "D").collect()
+            }
+        }
+    }
+}
+
+/// When two residues are in contact.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContactDefinition {
+    pub atoms: ContactAtoms,
+    /// Largest distance between any pair of their atoms, in A
+    pub cutoff: f32,
+    /// Smallest difference of residue numbers; 0 keeps every pair of distinct residues
+    pub min_separation: i32,
+}
+
+/// What to report for proteins too short for contact order to be meaningful.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortProteinPolicy {
+    /// Compute it whatever the length
+    Compute,
+    /// Report 0 below this many residues
+    ZeroBelow(usize),
+    /// Report nothing below this many residues
+    SkipBelow(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContactOrderOptions {
+    pub contacts: ContactDefinition,
+    pub short_protein: ShortProteinPolicy,
+}
+
+impl ContactOrderOptions {
+    /// Definition of [`crate::contact_order`]: CA atoms within 8 A, every pair, 0 below 20 residues.
+    pub const LEGACY: ContactOrderOptions = ContactOrderOptions {
+        contacts: ContactDefinition { atoms: ContactAtoms::Ca, cutoff: 8.0, min_separation: 0 },
+        short_protein: ShortProteinPolicy::ZeroBelow(20),
+    };
+}
+
+impl Default for ContactOrderOptions {
+    /// Plaxco et al. (1998): heavy atoms within 6 A, whatever the protein length.
+    fn default() -> Self {
+        ContactOrderOptions {
+            contacts: ContactDefinition { atoms: ContactAtoms::HeavyAtoms, cutoff: 6.0, min_separation: 1 },
+            short_protein: ShortProteinPolicy::Compute,
+        }
+    }
+}
+
+/// A pair of residues in contact, as indices into [`ResidueContacts::residues`] with `i < j`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contact {
+    pub i: usize,
+    pub j: usize,
+    /// Difference of their residue numbers
+    pub separation: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResidueContacts<'a> {
+    /// Polymer residues having at least one of the selected atoms, chain after chain
+    pub residues: Vec<ResidueView<'a>>,
+    pub contacts: Vec<Contact>,
+}
+
+/// A residue reduced to what contact detection needs.
+pub(crate) struct ContactSite<'a> {
+    pub(crate) chain_id: &'a str,
+    pub(crate) residue_seq: i32,
+    pub(crate) atoms: Vec<&'a AtomCoordinate>,
+}
+
+/// Pairs of sites `i < j` with atoms at most `cutoff` apart, whatever their chains, sorted.
+pub(crate) fn sites_within(sites: &[ContactSite], cutoff: f32) -> Vec<(usize, usize)> {
+    let (owners, positions): (Vec<usize>, Vec<[f32; 3]>) = sites
+        .iter()
+        .enumerate()
+        .flat_map(|(i, site)| site.atoms.iter().map(move |a| (i, [a.x, a.y, a.z])))
+        .unzip();
+    let mut pairs: Vec<(usize, usize)> = NeighbourSearch::new(positions)
+        .pairs_within(cutoff)
+        .into_iter()
+        .filter_map(|(a, b)| {
+            let (i, j) = (owners[a], owners[b]);
+            (i != j).then_some((i.min(j), i.max(j)))
+        })
+        .collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+    pairs
+}
+
+/// Intra-chain contacts between sites; interchain pairs have no sequence separation.
+pub(crate) fn find_contacts(sites: &[ContactSite], definition: &ContactDefinition) -> Vec<Contact> {
+    sites_within(sites, definition.cutoff)
+        .into_iter()
+        .filter_map(|(i, j)| {
+            let (a, b) = (&sites[i], &sites[j]);
+            let separation = (b.residue_seq - a.residue_seq).abs();
+            (a.chain_id == b.chain_id && separation >= definition.min_separation).then_some(Contact { i, j, separation })
+        })
+        .collect()
+}
+
+/// Polymer residues of a model with at least one of the atoms, chain after chain, and their contact sites.
+pub(crate) fn contact_sites<'a>(model: &ModelView<'a>, atoms: ContactAtoms) -> (Vec<ResidueView<'a>>, Vec<ContactSite<'a>>) {
+    model
+        .chains
+        .iter()
+        .flat_map(|chain| chain.polymer_residues())
+        .filter_map(|residue| {
+            let site = ContactSite {
+                chain_id: residue.chain_id(),
+                residue_seq: residue.residue_seq(),
+                atoms: atoms.select(residue),
+            };
+            (!site.atoms.is_empty()).then_some((*residue, site))
+        })
+        .unzip()
+}
+
+/// Residue contacts of a model.
+pub fn residue_contacts<'a>(model: &ModelView<'a>, definition: &ContactDefinition) -> ResidueContacts<'a> {
+    let (residues, sites) = contact_sites(model, definition.atoms);
+    let contacts = find_contacts(&sites, definition);
+    ResidueContacts { residues, contacts }
+}
+
+/// Contact order of a set of residues.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ContactOrder {
+    /// Mean sequence separation of the contacts over the number of residues, as a fraction
+    pub relative: f32,
+    /// Mean sequence separation of the contacts, in residues
+    pub absolute: f32,
+    pub contacts: usize,
+    pub residues: usize,
+}
+
+pub(crate) fn contact_order_of_sites(sites: &[ContactSite], options: &ContactOrderOptions) -> Option<ContactOrder> {
+    let length = sites.len();
+    match options.short_protein {
+        ShortProteinPolicy::ZeroBelow(min_length) if length < min_length => {
+            return Some(ContactOrder { residues: length, ..ContactOrder::default() })
+        }
+        ShortProteinPolicy::SkipBelow(min_length) if length < min_length => return None,
+        _ => {}
+    }
+
+    let contacts = find_contacts(sites, &options.contacts);
+    let n = contacts.len();
+    if n == 0 {
+        return Some(ContactOrder { residues: length, ..ContactOrder::default() });
+    }
+    let absolute = contacts.iter().map(|c| c.separation as f32).sum::<f32>() / n as f32;
+    Some(ContactOrder { relative: absolute / length as f32, absolute, contacts: n, residues: length })
+}
+
+/// Relative and absolute contact order of a model's polymer residues, `None` when skipped for its length.
+///
+/// Sequence separations come from residue numbers, so unmodeled stretches still count.
+pub fn contact_order_with(model: &ModelView, options: &ContactOrderOptions) -> Option<ContactOrder> {
+    let (_, sites) = contact_sites(model, options.contacts.atoms);
+    contact_order_of_sites(&sites, options)
+}
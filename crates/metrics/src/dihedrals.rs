@@ -0,0 +1,168 @@
+use pdb_io::{AtomCoordinate, Backbone, ModelView, ResidueView};
+
+use crate::breaks::MAX_PEPTIDE_BOND;
+
+/// Dihedral angle A-B-C-D in degrees, in (-180, 180].
+pub fn dihedral(a: &AtomCoordinate, b: &AtomCoordinate, c: &AtomCoordinate, d: &AtomCoordinate) -> f32 {
+    let sub = |u: &AtomCoordinate, v: &AtomCoordinate| [u.x - v.x, u.y - v.y, u.z - v.z];
+    let cross = |u: [f32; 3], v: [f32; 3]| [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+    let dot = |u: [f32; 3], v: [f32; 3]| u[0] * v[0] + u[1] * v[1] + u[2] * v[2];
+
+    let (b1, b2, b3) = (sub(b, a), sub(c, b), sub(d, c));
+    let (n1, n2) = (cross(b1, b2), cross(b2, b3));
+    let b2_norm = dot(b2, b2).sqrt();
+    let m1 = cross([b2[0] / b2_norm, b2[1] / b2_norm, b2[2] / b2_norm], n1);
+    dot(m1, n2).atan2(dot(n1, n2)).to_degrees()
+}
+
+/// Backbone dihedrals of one residue; angles needing a missing or unbonded neighbour are `None`.
+#[derive(Debug, Clone, Copy)]
+pub struct BackboneDihedrals<'a> {
+    pub residue: ResidueView<'a>,
+    /// C(i-1)-N-CA-C
+    pub phi: Option<f32>,
+    /// N-CA-C-N(i+1)
+    pub psi: Option<f32>,
+    /// CA(i-1)-C(i-1)-N-CA, about 180 for trans peptides
+    pub omega: Option<f32>,
+}
+
+impl BackboneDihedrals<'_> {
+    /// Ramachandran region, for residues with both phi and psi.
+    pub fn ramachandran(&self) -> Option<RamachandranRegion> {
+        let class = ResidueClass::of(self.residue.name());
+        Some(RamachandranRegion::classify(self.phi?, self.psi?, class))
+    }
+}
+
+fn bonded(previous: &Backbone, next: &Backbone) -> bool {
+    let (c, n) = (previous.c, next.n);
+    let (dx, dy, dz) = (c.x - n.x, c.y - n.y, c.z - n.z);
+    (dx * dx + dy * dy + dz * dz).sqrt() <= MAX_PEPTIDE_BOND
+}
+
+/// Phi, psi and omega of every polymer residue with an N, CA, C backbone, chain after chain.
+pub fn backbone_dihedrals<'a>(model: &ModelView<'a>) -> Vec<BackboneDihedrals<'a>> {
+    let mut dihedrals = Vec::new();
+
+    for chain in &model.chains {
+        let residues: Vec<(ResidueView<'a>, Backbone<'a>)> = chain
+            .polymer_residues()
+            .filter_map(|residue| Some((*residue, residue.backbone()?)))
+            .collect();
+
+        for (i, &(residue, current)) in residues.iter().enumerate() {
+            let previous = i.checked_sub(1).map(|p| residues[p].1).filter(|p| bonded(p, &current));
+            let next = residues.get(i + 1).map(|n| n.1).filter(|n| bonded(&current, n));
+
+            dihedrals.push(BackboneDihedrals {
+                residue,
+                phi: previous.map(|p| dihedral(p.c, current.n, current.ca, current.c)),
+                psi: next.map(|n| dihedral(current.n, current.ca, current.c, n.n)),
+                omega: previous.map(|p| dihedral(p.ca, p.c, current.n, current.ca)),
+            });
+        }
+    }
+
+    dihedrals
+}
+
+/// Residue types with distinct Ramachandran distributions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResidueClass {
+    General,
+    Glycine,
+    Proline,
+}
+
+impl ResidueClass {
+    pub fn of(residue_name: &str) -> Self {
+        match residue_name {
+            "GLY" => ResidueClass::Glycine,
+            "PRO" | "HYP" => ResidueClass::Proline,
+            _ => ResidueClass::General,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RamachandranRegion {
+    Favoured,
+    Allowed,
+    Outlier,
+}
+
+/// (phi min, phi max, psi min, psi max) boxes, in degrees.
+type Boxes = &'static [(f32, f32, f32, f32)];
+
+// Coarse boxes around the Lovell et al. (2003) contours: beta, PPII / alpha-R and alpha-L basins.
+const GENERAL_FAVOURED: Boxes = &[
+    (-180.0, -45.0, 100.0, 180.0),
+    (-180.0, -45.0, -180.0, -170.0),
+    (-160.0, -45.0, -70.0, -5.0),
+];
+const GENERAL_ALLOWED: Boxes = &[
+    (-180.0, -30.0, -180.0, 180.0),
+    (30.0, 100.0, -30.0, 100.0),
+];
+const PROLINE_FAVOURED: Boxes = &[
+    (-95.0, -45.0, 100.0, 180.0),
+    (-95.0, -45.0, -60.0, -5.0),
+];
+const PROLINE_ALLOWED: Boxes = &[(-110.0, -30.0, -180.0, 180.0)];
+
+fn in_boxes(phi: f32, psi: f32, boxes: Boxes) -> bool {
+    boxes
+        .iter()
+        .any(|&(phi_min, phi_max, psi_min, psi_max)| (phi_min..=phi_max).contains(&phi) && (psi_min..=psi_max).contains(&psi))
+}
+
+impl RamachandranRegion {
+    /// Classifies a (phi, psi) pair with coarse rectangular regions.
+    ///
+    /// Glycine, lacking a side chain, is favoured in the mirror images of the
+    /// general regions too and only an outlier close to phi = 0.
+    pub fn classify(phi: f32, psi: f32, class: ResidueClass) -> Self {
+        let (favoured, allowed) = match class {
+            ResidueClass::General => (in_boxes(phi, psi, GENERAL_FAVOURED), in_boxes(phi, psi, GENERAL_ALLOWED)),
+            ResidueClass::Proline => (in_boxes(phi, psi, PROLINE_FAVOURED), in_boxes(phi, psi, PROLINE_ALLOWED)),
+            ResidueClass::Glycine => (
+                in_boxes(phi, psi, GENERAL_FAVOURED) || in_boxes(-phi, -psi, GENERAL_FAVOURED),
+                phi.abs() >= 30.0,
+            ),
+        };
+
+        if favoured {
+            RamachandranRegion::Favoured
+        } else if allowed {
+            RamachandranRegion::Allowed
+        } else {
+            RamachandranRegion::Outlier
+        }
+    }
+}
+
+/// Fractions of classified residues in each region, each between 0 and 1.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RamachandranSummary {
+    pub favoured: f32,
+    pub allowed: f32,
+    pub outlier: f32,
+    /// Residues with both phi and psi
+    pub classified: usize,
+}
+
+pub fn ramachandran_summary(dihedrals: &[BackboneDihedrals]) -> RamachandranSummary {
+    let regions: Vec<RamachandranRegion> = dihedrals.iter().filter_map(BackboneDihedrals::ramachandran).collect();
+    let n = regions.len();
+    if n == 0 {
+        return RamachandranSummary::default();
+    }
+    let fraction = |region| regions.iter().filter(|&&r| r == region).count() as f32 / n as f32;
+    RamachandranSummary {
+        favoured: fraction(RamachandranRegion::Favoured),
+        allowed: fraction(RamachandranRegion::Allowed),
+        outlier: fraction(RamachandranRegion::Outlier),
+        classified: n,
+    }
+}
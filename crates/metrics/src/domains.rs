@@ -0,0 +1,322 @@
+use std::ops::Range;
+
+use pdb_io::{ModelView, PredictedAlignedError, ResidueView};
+
+use crate::neighbours::NeighbourSearch;
+
+/// Residue graph the domains are cut from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DomainMethod {
+    /// CA atoms closer than [`DomainOptions::contact_cutoff`] are linked
+    #[default]
+    Contacts,
+    /// Residues placed with a symmetrised PAE under [`DomainOptions::pae_cutoff`] are linked, weighted by 1/PAE^2
+    Pae,
+}
+
+/// Graph and stopping criteria of [`parse_domains`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DomainOptions {
+    pub method: DomainMethod,
+    /// CA-CA distance of the contact graph, in A
+    pub contact_cutoff: f32,
+    /// PAE from which residues are not linked, in A
+    pub pae_cutoff: f32,
+    /// Fewest residues of a domain; smaller groups are left unassigned
+    pub min_domain_size: usize,
+    /// Highest normalised cut at which a group is still split in two, between 0 and 2
+    pub max_cut: f32,
+    /// Shortest run of sequence-consecutive residues kept in a domain; shorter runs join a flanking domain
+    pub min_segment: usize,
+    /// Residues whose CA B-factor (pLDDT) is under this value are left out, as disordered linkers
+    pub min_plddt: Option<f32>,
+}
+
+impl Default for DomainOptions {
+    fn default() -> Self {
+        DomainOptions {
+            method: DomainMethod::Contacts,
+            contact_cutoff: 8.0,
+            pae_cutoff: 5.0,
+            min_domain_size: 30,
+            max_cut: 0.2,
+            min_segment: 10,
+            min_plddt: None,
+        }
+    }
+}
+
+/// A structural domain, possibly discontinuous.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Domain {
+    /// Runs of [`Domains::residues`] indices, in sequence order and never spanning two chains
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl Domain {
+    pub fn len(&self) -> usize {
+        self.ranges.iter().map(Range::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Residue indices of the domain, in sequence order.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.ranges.iter().flat_map(Range::clone)
+    }
+}
+
+/// Domains of a model.
+#[derive(Debug, Clone)]
+pub struct Domains<'a> {
+    /// Graph actually used: PAE falls back to contacts without a matching matrix
+    pub method: DomainMethod,
+    /// Residues with a CA atom, in the order of [`ModelView::ca_atoms`] and of the PAE rows
+    pub residues: Vec<ResidueView<'a>>,
+    /// Ordered by first residue
+    pub domains: Vec<Domain>,
+}
+
+impl Domains<'_> {
+    /// Residues outside every domain: linkers, disordered or isolated residues.
+    pub fn unassigned(&self) -> usize {
+        self.residues.len() - self.domains.iter().map(Domain::len).sum::<usize>()
+    }
+}
+
+/// Weighted undirected graph as adjacency lists, each edge stored on both ends.
+type Graph = Vec<Vec<(usize, f32)>>;
+
+fn contact_graph(residues: &[ResidueView], keep: &[bool], cutoff: f32) -> Graph {
+    let ca_atoms: Vec<_> = residues.iter().filter_map(ResidueView::ca).collect();
+    let mut graph = vec![Vec::new(); residues.len()];
+    for (i, j) in NeighbourSearch::from_atoms(&ca_atoms).pairs_within(cutoff) {
+        if keep[i] && keep[j] {
+            graph[i].push((j, 1.0));
+            graph[j].push((i, 1.0));
+        }
+    }
+    graph
+}
+
+fn pae_graph(pae: &PredictedAlignedError, keep: &[bool], cutoff: f32) -> Graph {
+    let mut graph = vec![Vec::new(); pae.size];
+    for i in 0..pae.size {
+        for j in i + 1..pae.size {
+            let value = (pae.get(i, j) + pae.get(j, i)) / 2.0;
+            if keep[i] && keep[j] && value < cutoff {
+                // Floor at 1 A so near-zero errors do not swamp the graph.
+                let weight = 1.0 / value.max(1.0).powi(2);
+                graph[i].push((j, weight));
+                graph[j].push((i, weight));
+            }
+        }
+    }
+    graph
+}
+
+/// Connected components of the nodes of `graph` in `nodes`, each sorted.
+fn components(graph: &Graph, nodes: &[usize], member: &mut [bool]) -> Vec<Vec<usize>> {
+    for &node in nodes {
+        member[node] = true;
+    }
+    let mut components = Vec::new();
+    for &start in nodes {
+        if !member[start] {
+            continue;
+        }
+        member[start] = false;
+        let mut component = vec![start];
+        let mut next = 0;
+        while next < component.len() {
+            for &(neighbour, _) in &graph[component[next]] {
+                if member[neighbour] {
+                    member[neighbour] = false;
+                    component.push(neighbour);
+                }
+            }
+            next += 1;
+        }
+        component.sort_unstable();
+        components.push(component);
+    }
+    components
+}
+
+/// Best two-way split of a connected group by normalised cut, along its Fiedler vector.
+///
+/// Returns the cut value and the two parts, both of at least `min_size` nodes.
+fn bisect(graph: &Graph, nodes: &[usize], min_size: usize, local: &mut [usize]) -> Option<(f32, Vec<usize>, Vec<usize>)> {
+    let n = nodes.len();
+    if n < 2 * min_size {
+        return None;
+    }
+    for (k, &node) in nodes.iter().enumerate() {
+        local[node] = k;
+    }
+    let in_group = |node: usize| local[node] < n && nodes[local[node]] == node;
+    let edges: Vec<Vec<(usize, f32)>> = nodes
+        .iter()
+        .map(|&node| graph[node].iter().filter(|&&(m, _)| in_group(m)).map(|&(m, w)| (local[m], w)).collect())
+        .collect();
+    let degree: Vec<f64> = edges.iter().map(|e| e.iter().map(|&(_, w)| w as f64).sum()).collect();
+    let total: f64 = degree.iter().sum();
+    if total == 0.0 {
+        return None;
+    }
+
+    // Power iteration on I + D^-1/2 W D^-1/2, orthogonal to its top eigenvector sqrt(D),
+    // converges to the second eigenvector of the normalised Laplacian. Starting from the
+    // sequence position favours the sequence-contiguous domains most proteins have.
+    let root: Vec<f64> = degree.iter().map(|d| d.sqrt()).collect();
+    let deflate = |x: &mut Vec<f64>| {
+        let dot: f64 = x.iter().zip(&root).map(|(a, b)| a * b).sum::<f64>() / total;
+        let norm = x.iter().zip(&root).map(|(a, b)| (a - dot * b).powi(2)).sum::<f64>().sqrt();
+        for (a, b) in x.iter_mut().zip(&root) {
+            *a = if norm > 0.0 { (*a - dot * b) / norm } else { 0.0 };
+        }
+    };
+    let mut x: Vec<f64> = (0..n).map(|k| k as f64 - (n - 1) as f64 / 2.0).collect();
+    deflate(&mut x);
+    for _ in 0..MAX_ITERATIONS {
+        let mut y: Vec<f64> = (0..n)
+            .map(|k| {
+                let spread: f64 = edges[k].iter().filter(|&&(m, _)| root[m] > 0.0).map(|&(m, w)| w as f64 * x[m] / root[m]).sum();
+                x[k] + if root[k] > 0.0 { spread / root[k] } else { 0.0 }
+            })
+            .collect();
+        deflate(&mut y);
+        let change: f64 = x.iter().zip(&y).map(|(a, b)| (a - b).powi(2)).sum();
+        x = y;
+        if change < CONVERGENCE {
+            break;
+        }
+    }
+
+    // Sweep the nodes by Fiedler value, moving one at a time to the first part.
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| {
+        let value = |k: usize| if root[k] > 0.0 { x[k] / root[k] } else { 0.0 };
+        value(a).total_cmp(&value(b)).then(a.cmp(&b))
+    });
+    let mut first = vec![false; n];
+    let (mut cut, mut volume) = (0.0f64, 0.0f64);
+    let mut best: Option<(f64, usize)> = None;
+    for (moved, &k) in order.iter().enumerate().take(n - min_size) {
+        first[k] = true;
+        volume += degree[k];
+        cut += edges[k].iter().map(|&(m, w)| if first[m] { -(w as f64) } else { w as f64 }).sum::<f64>();
+        if moved + 1 >= min_size && volume > 0.0 && volume < total {
+            let ncut = cut / volume + cut / (total - volume);
+            if best.is_none_or(|(b, _)| ncut < b) {
+                best = Some((ncut, moved + 1));
+            }
+        }
+    }
+    let (ncut, split) = best?;
+    let mut parts = (Vec::new(), Vec::new());
+    for (position, &k) in order.iter().enumerate() {
+        if position < split { &mut parts.0 } else { &mut parts.1 }.push(nodes[k]);
+    }
+    parts.0.sort_unstable();
+    parts.1.sort_unstable();
+    Some((ncut as f32, parts.0, parts.1))
+}
+
+const MAX_ITERATIONS: usize = 1000;
+const CONVERGENCE: f64 = 1e-12;
+
+/// Recursively splits the connected components of `nodes` while the cut stays under `max_cut`.
+fn split(graph: &Graph, nodes: &[usize], options: &DomainOptions, scratch: &mut (Vec<bool>, Vec<usize>), groups: &mut Vec<Vec<usize>>) {
+    for component in components(graph, nodes, &mut scratch.0) {
+        if component.len() < options.min_domain_size.max(1) {
+            continue;
+        }
+        match bisect(graph, &component, options.min_domain_size.max(1), &mut scratch.1) {
+            Some((ncut, a, b)) if ncut < options.max_cut => {
+                split(graph, &a, options, scratch, groups);
+                split(graph, &b, options, scratch, groups);
+            }
+            _ => groups.push(component),
+        }
+    }
+}
+
+/// Runs of consecutive residues with the same label, never spanning two chains.
+fn label_runs(residues: &[ResidueView], labels: &[Option<usize>]) -> Vec<(Range<usize>, Option<usize>)> {
+    let mut runs: Vec<(Range<usize>, Option<usize>)> = Vec::new();
+    for (i, &label) in labels.iter().enumerate() {
+        match runs.last_mut() {
+            Some((range, l)) if *l == label && residues[range.start].chain_id() == residues[i].chain_id() => range.end = i + 1,
+            _ => runs.push((i..i + 1, label)),
+        }
+    }
+    runs
+}
+
+/// Structural domains of a model, by recursive spectral bisection of a residue graph.
+///
+/// Each connected group of the graph is split along the Fiedler vector of its normalised
+/// Laplacian at the lowest normalised cut, as long as that cut is under
+/// [`DomainOptions::max_cut`] and both halves keep [`DomainOptions::min_domain_size`] residues.
+/// Sequence runs shorter than [`DomainOptions::min_segment`] then join the preceding domain
+/// of the chain, or the following one at the chain start.
+///
+/// [`DomainMethod::Pae`] needs `pae` with one row per residue with a CA atom, and otherwise
+/// falls back to the contact graph.
+pub fn parse_domains<'a>(model: &ModelView<'a>, options: &DomainOptions, pae: Option<&PredictedAlignedError>) -> Domains<'a> {
+    let residues: Vec<ResidueView<'a>> = model.residues().filter(|residue| residue.ca().is_some()).copied().collect();
+    let n = residues.len();
+    let keep: Vec<bool> = residues
+        .iter()
+        .map(|residue| options.min_plddt.is_none_or(|min| residue.ca().is_some_and(|ca| ca.bfactor >= min)))
+        .collect();
+
+    let pae = pae.filter(|pae| options.method == DomainMethod::Pae && pae.size == n);
+    let (method, graph) = match pae {
+        Some(pae) => (DomainMethod::Pae, pae_graph(pae, &keep, options.pae_cutoff)),
+        None => (DomainMethod::Contacts, contact_graph(&residues, &keep, options.contact_cutoff)),
+    };
+
+    let nodes: Vec<usize> = (0..n).filter(|&i| keep[i]).collect();
+    let mut groups = Vec::new();
+    split(&graph, &nodes, options, &mut (vec![false; n], vec![usize::MAX; n]), &mut groups);
+    let mut labels = vec![None; n];
+    for (label, group) in groups.iter().enumerate() {
+        for &i in group {
+            labels[i] = Some(label);
+        }
+    }
+
+    // Short excursions into another domain are assigned to the flanking one.
+    let runs = label_runs(&residues, &labels);
+    for (k, (range, label)) in runs.iter().enumerate() {
+        if label.is_none() || range.len() >= options.min_segment {
+            continue;
+        }
+        let same_chain = |other: &(Range<usize>, Option<usize>)| residues[other.0.start].chain_id() == residues[range.start].chain_id();
+        let flank = k
+            .checked_sub(1)
+            .map(|p| &runs[p])
+            .filter(|run| same_chain(run) && run.1.is_some())
+            .or_else(|| runs.get(k + 1).filter(|run| same_chain(run) && run.1.is_some()));
+        if let Some(&(_, Some(flank))) = flank {
+            if flank != label.unwrap() {
+                labels[range.clone()].fill(Some(flank));
+            }
+        }
+    }
+
+    let mut domains: Vec<Domain> = vec![Domain::default(); groups.len()];
+    for (range, label) in label_runs(&residues, &labels) {
+        if let Some(label) = label {
+            domains[label].ranges.push(range);
+        }
+    }
+    domains.retain(|domain| domain.len() >= options.min_domain_size.max(1));
+    domains.sort_by_key(|domain| domain.ranges[0].start);
+
+    Domains { method, residues, domains }
+}
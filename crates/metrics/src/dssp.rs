@@ -0,0 +1,405 @@
+use pdb_io::{AtomCoordinate, ModelView, ResidueView};
+
+use crate::neighbours::NeighbourSearch;
+
+/// Secondary structure states of the DSSP 8-state alphabet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondaryStructure {
+    /// H, alpha helix
+    AlphaHelix,
+    /// B, isolated beta bridge
+    Bridge,
+    /// E, extended strand in a ladder
+    Strand,
+    /// G, 3-10 helix
+    Helix310,
+    /// I, pi helix
+    PiHelix,
+    /// T, hydrogen-bonded turn
+    Turn,
+    /// S, bend
+    Bend,
+    /// Coil, written as `-`
+    Coil,
+}
+
+impl SecondaryStructure {
+    pub fn code(self) -> char {
+        match self {
+            SecondaryStructure::AlphaHelix => 'H',
+            SecondaryStructure::Bridge => 'B',
+            SecondaryStructure::Strand => 'E',
+            SecondaryStructure::Helix310 => 'G',
+            SecondaryStructure::PiHelix => 'I',
+            SecondaryStructure::Turn => 'T',
+            SecondaryStructure::Bend => 'S',
+            SecondaryStructure::Coil => '-',
+        }
+    }
+
+    /// H, G and I.
+    pub fn is_helix(self) -> bool {
+        matches!(self, SecondaryStructure::AlphaHelix | SecondaryStructure::Helix310 | SecondaryStructure::PiHelix)
+    }
+
+    /// E and B.
+    pub fn is_strand(self) -> bool {
+        matches!(self, SecondaryStructure::Strand | SecondaryStructure::Bridge)
+    }
+}
+
+/// Helix, strand and coil fractions, each between 0 and 1.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SecondaryStructureFractions {
+    pub helix: f32,
+    pub strand: f32,
+    pub coil: f32,
+}
+
+/// DSSP assignment of a model.
+#[derive(Debug, Clone)]
+pub struct Dssp<'a> {
+    /// Residues with a complete N, CA, C backbone, chain after chain
+    pub residues: Vec<ResidueView<'a>>,
+    /// State of each residue of `residues`
+    pub states: Vec<SecondaryStructure>,
+    /// Hydrogen bonds of each residue's N-H, as (index in `residues` of the C=O, energy in
+    /// kcal/mol): at most the two lowest-energy ones below [`MAX_HBOND_ENERGY`], lowest first
+    pub acceptors: Vec<Vec<(usize, f32)>>,
+}
+
+impl Dssp<'_> {
+    /// One character per residue, e.g. `--HHHHHHHHTT-EEEE-`.
+    pub fn string(&self) -> String {
+        self.states.iter().map(|s| s.code()).collect()
+    }
+
+    /// Fractions of helix (H, G, I), strand (E, B) and everything else.
+    pub fn fractions(&self) -> SecondaryStructureFractions {
+        let n = self.states.len();
+        if n == 0 {
+            return SecondaryStructureFractions::default();
+        }
+        let helix = self.states.iter().filter(|s| s.is_helix()).count() as f32 / n as f32;
+        let strand = self.states.iter().filter(|s| s.is_strand()).count() as f32 / n as f32;
+        SecondaryStructureFractions { helix, strand, coil: 1.0 - helix - strand }
+    }
+}
+
+/// Kabsch-Sander coupling constant, q1 * q2 * f = 0.42 * 0.20 * 332 kcal/mol.
+const COUPLING: f32 = 0.084 * 332.0;
+/// Hydrogen bonds are counted below this energy, in kcal/mol.
+pub const MAX_HBOND_ENERGY: f32 = -0.5;
+const MIN_HBOND_ENERGY: f32 = -9.9;
+/// Residue pairs whose alpha-carbons are further apart cannot be hydrogen-bonded.
+const MAX_CA_DISTANCE: f32 = 9.0;
+/// C-N distance above which DSSP assumes a chain break: `kMaxPeptideBondLength` of the
+/// reference DSSP implementation, kept so assignments match it. Looser than
+/// [`crate::MAX_PEPTIDE_BOND`] of the chain break report, which flags stretched bonds too.
+const DSSP_BREAK_DISTANCE: f32 = 2.5;
+
+type Point = [f32; 3];
+
+fn point(atom: &AtomCoordinate) -> Point {
+    [atom.x, atom.y, atom.z]
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: Point, b: Point) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    dot(sub(a, b), sub(a, b)).sqrt()
+}
+
+/// Electrostatic energy of the N-H..O=C hydrogen bond, in kcal/mol.
+///
+/// E = q1 q2 f (1/r(ON) + 1/r(CH) - 1/r(OH) - 1/r(CN)), capped at -9.9 kcal/mol
+/// for overlapping atoms as in DSSP.
+pub fn hbond_energy(n: Point, h: Point, c: Point, o: Point) -> f32 {
+    let (r_on, r_ch, r_oh, r_cn) = (distance(o, n), distance(c, h), distance(o, h), distance(c, n));
+    if r_on < 0.5 || r_ch < 0.5 || r_oh < 0.5 || r_cn < 0.5 {
+        return MIN_HBOND_ENERGY;
+    }
+    (COUPLING * (1.0 / r_on + 1.0 / r_ch - 1.0 / r_oh - 1.0 / r_cn)).max(MIN_HBOND_ENERGY)
+}
+
+struct BackboneResidue {
+    n: Point,
+    ca: Point,
+    c: Point,
+    o: Option<Point>,
+    /// Amide hydrogen, placed from the previous carbonyl; absent for prolines and after breaks
+    h: Option<Point>,
+    /// Two lowest-energy acceptors of this residue's N-H, as (residue index, energy)
+    acceptors: [(usize, f32); 2],
+}
+
+impl BackboneResidue {
+    fn add_acceptor(&mut self, acceptor: usize, energy: f32) {
+        if energy < self.acceptors[0].1 {
+            self.acceptors[1] = self.acceptors[0];
+            self.acceptors[0] = (acceptor, energy);
+        } else if energy < self.acceptors[1].1 {
+            self.acceptors[1] = (acceptor, energy);
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BridgeKind {
+    Parallel,
+    Antiparallel,
+}
+
+struct Ladder {
+    kind: BridgeKind,
+    /// (i, j) pairs with i < j, in order of increasing i
+    bridges: Vec<(usize, usize)>,
+    /// Index of the ladder this one is linked to by a beta bulge
+    group: usize,
+}
+
+struct Assignment {
+    residues: Vec<BackboneResidue>,
+    /// Number of chain breaks before each residue, to test continuity in O(1)
+    breaks_before: Vec<usize>,
+}
+
+impl Assignment {
+    fn no_break(&self, from: usize, to: usize) -> bool {
+        self.breaks_before[from] == self.breaks_before[to]
+    }
+
+    /// Whether the C=O of `acceptor` is hydrogen-bonded to the N-H of `donor`.
+    fn hbond(&self, acceptor: usize, donor: usize) -> bool {
+        self.residues[donor]
+            .acceptors
+            .iter()
+            .any(|&(partner, energy)| partner == acceptor && energy < MAX_HBOND_ENERGY)
+    }
+
+    /// n-turn at i: Hbond(i, i + n) within a continuous stretch.
+    fn turn(&self, i: usize, n: usize) -> bool {
+        i + n < self.residues.len() && self.no_break(i, i + n) && self.hbond(i, i + n)
+    }
+
+    fn bridge(&self, i: usize, j: usize) -> Option<BridgeKind> {
+        let len = self.residues.len();
+        if i == 0 || j + 1 >= len || !self.no_break(i - 1, i + 1) || !self.no_break(j - 1, j + 1) {
+            return None;
+        }
+        if (self.hbond(i - 1, j) && self.hbond(j, i + 1)) || (self.hbond(j - 1, i) && self.hbond(i, j + 1)) {
+            Some(BridgeKind::Parallel)
+        } else if (self.hbond(i, j) && self.hbond(j, i)) || (self.hbond(i - 1, j + 1) && self.hbond(j - 1, i + 1)) {
+            Some(BridgeKind::Antiparallel)
+        } else {
+            None
+        }
+    }
+}
+
+/// Assigns DSSP secondary structure (Kabsch & Sander, 1983) to the residues of a model.
+///
+/// Only residues with N, CA and C atoms are considered; the carbonyl oxygen is
+/// needed for a residue to accept hydrogen bonds. Chains are delimited by
+/// chain changes and by C-N distances over 2.5 A.
+pub fn dssp<'a>(model: &ModelView<'a>) -> Dssp<'a> {
+    let mut residues: Vec<ResidueView<'a>> = Vec::new();
+    let mut backbone: Vec<BackboneResidue> = Vec::new();
+    let mut breaks_before: Vec<usize> = Vec::new();
+    let mut breaks = 0;
+
+    for chain in &model.chains {
+        let mut previous: Option<usize> = None;
+        for residue in chain.polymer_residues() {
+            let Some(atoms) = residue.backbone() else {
+                previous = None;
+                continue;
+            };
+            let (n, ca, c, o) = (point(atoms.n), point(atoms.ca), point(atoms.c), atoms.o.map(point));
+
+            let bonded = previous.filter(|&p| distance(backbone[p].c, n) <= DSSP_BREAK_DISTANCE);
+            if bonded.is_none() {
+                breaks += 1;
+            }
+            let h = match bonded.and_then(|p| Some((backbone[p].c, backbone[p].o?))) {
+                Some((prev_c, prev_o)) if residue.name() != "PRO" => {
+                    let co = sub(prev_c, prev_o);
+                    let norm = dot(co, co).sqrt();
+                    Some([n[0] + co[0] / norm, n[1] + co[1] / norm, n[2] + co[2] / norm])
+                }
+                _ => None,
+            };
+
+            previous = Some(backbone.len());
+            residues.push(*residue);
+            breaks_before.push(breaks);
+            backbone.push(BackboneResidue { n, ca, c, o, h, acceptors: [(usize::MAX, 0.0); 2] });
+        }
+    }
+
+    let len = backbone.len();
+    let search = NeighbourSearch::new(backbone.iter().map(|r| r.ca).collect());
+    for donor in 0..len {
+        let Some(h) = backbone[donor].h else { continue };
+        // Sorted so that equal energies keep the first acceptor, whatever the tree layout
+        let mut acceptors: Vec<usize> = search.within(backbone[donor].ca, MAX_CA_DISTANCE).map(|(i, _)| i).collect();
+        acceptors.sort_unstable();
+        for acceptor in acceptors {
+            // The N-H of a residue never bonds its own C=O nor the one just before it.
+            if acceptor == donor || acceptor + 1 == donor {
+                continue;
+            }
+            let Some(o) = backbone[acceptor].o else { continue };
+            let energy = hbond_energy(backbone[donor].n, h, backbone[acceptor].c, o);
+            backbone[donor].add_acceptor(acceptor, energy);
+        }
+    }
+
+    let assignment = Assignment { residues: backbone, breaks_before };
+    let mut states = vec![SecondaryStructure::Coil; len];
+
+    assign_sheets(&assignment, &mut states);
+    assign_helices(&assignment, &mut states);
+    assign_turns_and_bends(&assignment, &mut states);
+
+    let acceptors = assignment
+        .residues
+        .iter()
+        .map(|r| r.acceptors.iter().copied().filter(|&(_, energy)| energy < MAX_HBOND_ENERGY).collect())
+        .collect();
+    Dssp { residues, states, acceptors }
+}
+
+/// Bridges, ladders and bulges: E for ladders, B for isolated bridges.
+fn assign_sheets(assignment: &Assignment, states: &mut [SecondaryStructure]) {
+    let len = states.len();
+    let mut ladders: Vec<Ladder> = Vec::new();
+
+    for i in 1..len.saturating_sub(1) {
+        for j in i + 3..len.saturating_sub(1) {
+            let Some(kind) = assignment.bridge(i, j) else { continue };
+
+            // Consecutive bridges of the same kind extend a ladder.
+            let extends = ladders.iter_mut().rev().find(|ladder| {
+                let &(last_i, last_j) = ladder.bridges.last().unwrap();
+                ladder.kind == kind
+                    && last_i + 1 == i
+                    && match kind {
+                        BridgeKind::Parallel => last_j + 1 == j,
+                        BridgeKind::Antiparallel => last_j == j + 1,
+                    }
+            });
+            match extends {
+                Some(ladder) => ladder.bridges.push((i, j)),
+                None => {
+                    let group = ladders.len();
+                    ladders.push(Ladder { kind, bridges: vec![(i, j)], group });
+                }
+            }
+        }
+    }
+
+    // Ladders of the same kind separated by a bulge (gaps under 6 and 3 residues) form one strand pair.
+    for b in 0..ladders.len() {
+        for a in 0..b {
+            let (a_ladder, b_ladder) = (&ladders[a], &ladders[b]);
+            if a_ladder.kind != b_ladder.kind {
+                continue;
+            }
+            let (a_first, a_last) = (a_ladder.bridges[0], *a_ladder.bridges.last().unwrap());
+            let (b_first, b_last) = (b_ladder.bridges[0], *b_ladder.bridges.last().unwrap());
+            let (gap_i, gap_j) = match a_ladder.kind {
+                BridgeKind::Parallel => (b_first.0 as i64 - a_last.0 as i64, b_first.1 as i64 - a_last.1 as i64),
+                BridgeKind::Antiparallel => (b_first.0 as i64 - a_last.0 as i64, a_last.1 as i64 - b_first.1 as i64),
+            };
+            let bulge = gap_i > 0 && gap_j > 0 && ((gap_i < 6 && gap_j < 3) || (gap_i < 3 && gap_j < 6));
+            // Strands must not overlap their partners.
+            if bulge && a_first.0 < b_last.1 {
+                let group = ladders[a].group;
+                ladders[b].group = group;
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<&Ladder>> = Vec::new();
+    let mut group_index = std::collections::HashMap::new();
+    for ladder in &ladders {
+        let index = *group_index.entry(ladder.group).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[index].push(ladder);
+    }
+
+    for group in groups {
+        let bridges: Vec<(usize, usize)> = group.iter().flat_map(|l| l.bridges.iter().copied()).collect();
+        if bridges.len() == 1 {
+            let (i, j) = bridges[0];
+            for k in [i, j].iter() {
+                if states[*k] != SecondaryStructure::Strand {
+                    states[*k] = SecondaryStructure::Bridge;
+                }
+            }
+            continue;
+        }
+        let i_span = bridges.iter().map(|b| b.0).min().unwrap()..=bridges.iter().map(|b| b.0).max().unwrap();
+        let j_span = bridges.iter().map(|b| b.1).min().unwrap()..=bridges.iter().map(|b| b.1).max().unwrap();
+        for k in i_span.chain(j_span) {
+            states[k] = SecondaryStructure::Strand;
+        }
+    }
+}
+
+/// Two consecutive n-turns make a minimal helix: H (n = 4) wins over strands,
+/// G (n = 3) and I (n = 5) only fill residues that are still unassigned.
+fn assign_helices(assignment: &Assignment, states: &mut [SecondaryStructure]) {
+    let len = states.len();
+    let helix_starts = |n: usize| (1..len).filter(move |&i| assignment.turn(i - 1, n) && assignment.turn(i, n));
+
+    for i in helix_starts(4) {
+        for state in &mut states[i..i + 4] {
+            *state = SecondaryStructure::AlphaHelix;
+        }
+    }
+    for (n, helix) in [(3, SecondaryStructure::Helix310), (5, SecondaryStructure::PiHelix)].iter().copied() {
+        for i in helix_starts(n) {
+            let span = &mut states[i..i + n];
+            if span.iter().all(|&s| s == SecondaryStructure::Coil || s == helix) {
+                span.iter_mut().for_each(|s| *s = helix);
+            }
+        }
+    }
+}
+
+/// T for residues inside an n-turn, otherwise S where the CA trace bends by more than 70 degrees.
+fn assign_turns_and_bends(assignment: &Assignment, states: &mut [SecondaryStructure]) {
+    let len = states.len();
+    for i in 0..len {
+        if states[i] != SecondaryStructure::Coil {
+            continue;
+        }
+        let in_turn = [3usize, 4, 5]
+            .iter()
+            .any(|&n| (1..n).any(|k| i >= k && assignment.turn(i - k, n)));
+        if in_turn {
+            states[i] = SecondaryStructure::Turn;
+            continue;
+        }
+
+        if i >= 2 && i + 2 < len && assignment.no_break(i - 2, i + 2) {
+            let residues = &assignment.residues;
+            let before = sub(residues[i].ca, residues[i - 2].ca);
+            let after = sub(residues[i + 2].ca, residues[i].ca);
+            let cosine = dot(before, after) / (dot(before, before) * dot(after, after)).sqrt();
+            if cosine.clamp(-1.0, 1.0).acos().to_degrees() > 70.0 {
+                states[i] = SecondaryStructure::Bend;
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+
+use pdb_io::AtomCoordinate;
+
+type Point = [f64; 3];
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: Point, b: Point) -> Point {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn dot(a: Point, b: Point) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn norm(a: Point) -> f64 {
+    dot(a, a).sqrt()
+}
+
+/// Convex hull of a set of atoms.
+#[derive(Debug, Clone, Default)]
+pub struct ConvexHull {
+    /// Triangles of the hull surface, as indices into the atoms, counter-clockwise seen from outside
+    pub faces: Vec<[usize; 3]>,
+    /// Enclosed volume, in A^3
+    pub volume: f32,
+    /// Surface area, in A^2
+    pub area: f32,
+}
+
+/// Signed distance-like measure of `p` above the plane of face (a, b, c), positive on the outer side.
+fn height(points: &[Point], face: &[usize; 3], p: Point) -> f64 {
+    let [a, b, c] = face.map(|i| points[i]);
+    dot(cross(sub(b, a), sub(c, a)), sub(p, a))
+}
+
+/// Index of the point maximising `score`.
+fn farthest(points: &[Point], score: impl Fn(Point) -> f64) -> (usize, f64) {
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, &p)| (i, score(p)))
+        .fold((0, f64::MIN), |best, candidate| if candidate.1 > best.1 { candidate } else { best })
+}
+
+/// Initial tetrahedron from extreme points, `None` when the points are coplanar.
+fn initial_tetrahedron(points: &[Point], epsilon: f64) -> Option<[usize; 4]> {
+    let (a, _) = farthest(points, |p| -p[0]);
+    let (b, ab) = farthest(points, |p| norm(sub(p, points[a])));
+    if ab <= epsilon {
+        return None;
+    }
+    let direction = sub(points[b], points[a]);
+    let (c, area) = farthest(points, |p| norm(cross(direction, sub(p, points[a]))));
+    if area <= epsilon * ab {
+        return None;
+    }
+    let normal = cross(direction, sub(points[c], points[a]));
+    let (d, volume) = farthest(points, |p| dot(normal, sub(p, points[a])).abs());
+    if volume <= epsilon * area {
+        return None;
+    }
+    Some([a, b, c, d])
+}
+
+/// Convex hull of the atoms, by incremental insertion.
+///
+/// Fewer than four atoms, or coplanar atoms, give an empty hull with zero volume and area.
+pub fn convex_hull(atoms: &[&AtomCoordinate]) -> ConvexHull {
+    let points: Vec<Point> = atoms.iter().map(|a| [a.x as f64, a.y as f64, a.z as f64]).collect();
+    if points.len() < 4 {
+        return ConvexHull::default();
+    }
+
+    let scale = points.iter().flat_map(|p| p.iter()).fold(0.0f64, |m, v| m.max(v.abs())).max(1.0);
+    let epsilon = 1e-9 * scale;
+    let Some([a, b, c, d]) = initial_tetrahedron(&points, epsilon) else {
+        return ConvexHull::default();
+    };
+
+    // Point strictly inside the hull, used to orient faces and sum tetrahedra.
+    let inside = [0, 1, 2].map(|k| (points[a][k] + points[b][k] + points[c][k] + points[d][k]) / 4.0);
+    let orient = |face: [usize; 3]| {
+        if height(&points, &face, inside) > 0.0 {
+            [face[0], face[2], face[1]]
+        } else {
+            face
+        }
+    };
+    let mut faces: Vec<[usize; 3]> = vec![orient([a, b, c]), orient([a, b, d]), orient([a, c, d]), orient([b, c, d])];
+
+    // Heights are areas times distances, so the tolerance scales with the face size.
+    let tolerance = |face: &[usize; 3]| {
+        let [p, q, r] = face.map(|i| points[i]);
+        epsilon * norm(cross(sub(q, p), sub(r, p)))
+    };
+
+    for (i, &p) in points.iter().enumerate() {
+        if i == a || i == b || i == c || i == d {
+            continue;
+        }
+        let visible: Vec<bool> = faces.iter().map(|face| height(&points, face, p) > tolerance(face)).collect();
+        if !visible.contains(&true) {
+            continue;
+        }
+
+        // Edges of visible faces whose opposite edge belongs to a hidden face
+        let visible_edges: HashSet<(usize, usize)> = faces
+            .iter()
+            .zip(&visible)
+            .filter(|(_, &v)| v)
+            .flat_map(|(f, _)| [(f[0], f[1]), (f[1], f[2]), (f[2], f[0])])
+            .collect();
+        let horizon: Vec<(usize, usize)> =
+            visible_edges.iter().filter(|&&(u, v)| !visible_edges.contains(&(v, u))).copied().collect();
+
+        let mut kept: Vec<[usize; 3]> = faces.iter().zip(&visible).filter(|(_, &v)| !v).map(|(f, _)| *f).collect();
+        kept.extend(horizon.into_iter().map(|(u, v)| [u, v, i]));
+        faces = kept;
+    }
+
+    let mut volume = 0.0;
+    let mut area = 0.0;
+    for face in &faces {
+        let [p, q, r] = face.map(|i| points[i]);
+        let normal = cross(sub(q, p), sub(r, p));
+        area += norm(normal) / 2.0;
+        volume += dot(normal, sub(p, inside)) / 6.0;
+    }
+
+    ConvexHull { faces, volume: volume as f32, area: area as f32 }
+}
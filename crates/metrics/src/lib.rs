@@ -1,12 +1,57 @@
-use pdb_io::ParsedPDB;
+use pdb_io::Model;
 use pdb_io::AtomCoordinate;
+use contacts::{contact_order_of_sites, ContactSite};
 //pub mod sasa;
+mod breaks;
+mod confidence;
+mod contacts;
+mod dihedrals;
+mod domains;
+mod dssp;
+mod hull;
+mod matrix;
+mod neighbours;
+mod pae;
+mod rotamers;
+mod shape;
+mod volume;
+
+pub use breaks::{chain_breaks, model_chain_breaks, ChainBreak, ChainBreaks, Segment, MAX_CA_CA_DISTANCE, MAX_PEPTIDE_BOND};
+pub use confidence::{
+    chain_confidence, confidence_profile, confidence_statistics, ChainConfidence, ConfidenceProfile, ConfidenceSource,
+    ConfidenceStatistics, ConfidenceThresholds, SegmentationOptions, ThresholdFraction,
+};
+pub use contacts::{
+    contact_order_with, residue_contacts, Contact, ContactAtoms, ContactDefinition, ContactOrder, ContactOrderOptions,
+    ResidueContacts, ShortProteinPolicy,
+};
+pub use dihedrals::{
+    backbone_dihedrals, dihedral, ramachandran_summary, BackboneDihedrals, RamachandranRegion, RamachandranSummary,
+    ResidueClass,
+};
+pub use domains::{parse_domains, Domain, DomainMethod, DomainOptions, Domains};
+pub use dssp::{dssp, hbond_energy, Dssp, SecondaryStructure, SecondaryStructureFractions, MAX_HBOND_ENERGY};
+pub use hull::{convex_hull, ConvexHull};
+pub use matrix::{contact_map, distance_matrix, ContactMap, DistanceMatrix, MatrixLayout, ResidueMatrix};
+pub use neighbours::NeighbourSearch;
+pub use pae::{pae_domain_summary, pae_summary, PaeSummary, CONFIDENT_PAE};
+pub use rotamers::{rotamer_summary, side_chain_dihedrals, RotamerFit, RotamerSummary, SideChainDihedrals, ROTAMER_TOLERANCE};
+pub use shape::{gyration_tensor, oriented_bounding_box, GyrationTensor, OrientedBoundingBox, ShapeDescriptors};
+pub use volume::{molecular_volume, van_der_waals_radius, MolecularVolume, VolumeOptions, DEFAULT_VDW_RADIUS};
+
+
+pub fn get_ca_atoms(model: &Model) -> Vec<&AtomCoordinate> {
+    // One alpha-carbon per residue, see `ResidueView::ca`.
+    model.view().ca_atoms()
+}
 
-
-pub fn get_ca_atoms<'a>(pdb: &'a ParsedPDB) -> Vec<&'a AtomCoordinate> {
-    pdb.atoms
-        .iter()
-        .filter(|atom| atom.atom_name.trim() == "CA")
+/// Non-hydrogen atoms of the polymer residues, without waters or ligands.
+pub fn get_heavy_atoms(model: &Model) -> Vec<&AtomCoordinate> {
+    let view = model.view();
+    view.residues()
+        .filter(|residue| residue.is_polymer())
+        .flat_map(|residue| residue.atoms)
+        .filter(|atom| atom.element != "H" && atom.element != "D")
         .collect()
 }
 pub fn radius_of_gyration(ca_atoms: &[&AtomCoordinate]) -> f32 {
@@ -34,35 +79,14 @@ pub fn radius_of_gyration(ca_atoms: &[&AtomCoordinate]) -> f32 {
     (sum_dist_sq / n as f32).sqrt()
 }
 
+/// Mean pLDDT of CA atoms and the percentage of them over 50, 70 and 90 pLDDT.
+///
+/// Percentages go from 0 to 100, unlike the fractions of [`confidence_statistics`],
+/// which also takes other thresholds and experimental B-factors.
 pub fn plddt_statistics(ca_atoms: &[&AtomCoordinate]) -> (f32, f32, f32, f32) {
-    /* Returns:
-       - mean pLDDT
-       - fraction of residues over 50 pLDDT
-       - fraction of residues over 70 pLDDT
-       - fraction of residues over 90 pLDDT    
-    */
-    let n = ca_atoms.len();
-    if n == 0 {
-        return (0.0, 0.0, 0.0, 0.0);
-    }
-
-    let (sum_plddt, count_50, count_70, count_90) = ca_atoms
-        .iter()
-        .fold((0.0, 0, 0, 0), |(sum, c50, c70, c90), ca| {
-            (
-                sum + ca.bfactor,
-                c50 + (ca.bfactor > 50.0) as usize,
-                c70 + (ca.bfactor > 70.0) as usize,
-                c90 + (ca.bfactor > 90.0) as usize,
-            )
-        });
-
-    let mean_plddt = sum_plddt / n as f32;
-    let fraction_50 = count_50 as f32 / n as f32 * 100.0;
-    let fraction_70 = count_70 as f32 / n as f32 * 100.0;
-    let fraction_90 = count_90 as f32 / n as f32 * 100.0;
-
-    (mean_plddt, fraction_50, fraction_70, fraction_90)
+    let statistics = confidence_statistics(ca_atoms, &ConfidenceThresholds::default());
+    let [above_50, above_70, above_90] = [0, 1, 2].map(|i| statistics.fractions[i].percent());
+    (statistics.mean, above_50, above_70, above_90)
 }
 
 pub fn bounding_box_volume(ca_atoms: &[&AtomCoordinate]) -> f32 {
@@ -94,35 +118,15 @@ pub fn bounding_box_volume(ca_atoms: &[&AtomCoordinate]) -> f32 {
     dx * dy * dz
 }
 
+/// Relative contact order of CA atoms in percent, 0 below 20 residues.
+///
+/// Preset of [`contact_order_with`], see [`ContactOrderOptions::LEGACY`].
 pub fn contact_order(ca_atoms: &[&AtomCoordinate]) -> f32 {
-    let l = ca_atoms.len();
-    if l < 20 {
-        return 0.0;
-    }
-
-    let mut sum_seqsep = 0.0;
-    let mut n = 0.0; // Number of contacts detected
-
-    for i in 0..l {
-        for j in (i + 1)..l {
-            let dx = ca_atoms[i].x - ca_atoms[j].x;
-            let dy = ca_atoms[i].y - ca_atoms[j].y;
-            let dz = ca_atoms[i].z - ca_atoms[j].z;
-            let dist = (dx * dx + dy * dy + dz * dz).sqrt();
-
-            if dist <= 8.0 {
-                let seq_sep = (ca_atoms[j].residue_seq - ca_atoms[i].residue_seq).abs() as f32;
-                sum_seqsep += seq_sep;
-                n += 1.0;
-            }
-        }
-    }
-
-    if n == 0.0 {
-        0.0
-    } else {
-        sum_seqsep / ((l as f32) * n) * 100.0
-    }
+    let sites: Vec<ContactSite> = ca_atoms
+        .iter()
+        .map(|&ca| ContactSite { chain_id: &ca.chain_id, residue_seq: ca.residue_seq, atoms: vec![ca] })
+        .collect();
+    contact_order_of_sites(&sites, &ContactOrderOptions::LEGACY).map_or(0.0, |co| co.relative * 100.0)
 }
 
 pub fn return_len(ca_atoms: &[&AtomCoordinate]) -> usize {
@@ -0,0 +1,160 @@
+use half::f16;
+use ndarray::Array2;
+use pdb_io::{ModelView, ResidueView};
+
+use crate::contacts::{contact_sites, sites_within, ContactAtoms, ContactSite};
+
+/// Storage of a symmetric residue-by-residue matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixLayout {
+    /// n x n, row-major
+    Full,
+    /// Upper triangle without the diagonal, row after row, n (n - 1) / 2 values (SciPy `pdist` order)
+    Condensed,
+}
+
+/// Symmetric matrix over the residues of a model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResidueMatrix<T> {
+    /// Number of residues
+    pub size: usize,
+    pub layout: MatrixLayout,
+    pub values: Vec<T>,
+    /// Value of every diagonal element, not stored by the condensed layout
+    pub diagonal: T,
+}
+
+impl<T: Copy> ResidueMatrix<T> {
+    /// Builds the matrix from the value of every pair `i < j`.
+    fn from_pairs(size: usize, layout: MatrixLayout, diagonal: T, value: impl Fn(usize, usize) -> T) -> Self {
+        let values = match layout {
+            MatrixLayout::Full => {
+                let mut values = vec![diagonal; size * size];
+                for i in 0..size {
+                    for j in (i + 1)..size {
+                        let v = value(i, j);
+                        values[i * size + j] = v;
+                        values[j * size + i] = v;
+                    }
+                }
+                values
+            }
+            MatrixLayout::Condensed => {
+                (0..size).flat_map(|i| ((i + 1)..size).map(move |j| (i, j))).map(|(i, j)| value(i, j)).collect()
+            }
+        };
+        ResidueMatrix { size, layout, values, diagonal }
+    }
+
+    /// Dimensions of `values`: `[n, n]` or `[n (n - 1) / 2]`.
+    pub fn shape(&self) -> Vec<usize> {
+        match self.layout {
+            MatrixLayout::Full => vec![self.size, self.size],
+            MatrixLayout::Condensed => vec![self.values.len()],
+        }
+    }
+
+    /// Position of off-diagonal element (i, j) in `values`.
+    fn offset(&self, i: usize, j: usize) -> usize {
+        match self.layout {
+            MatrixLayout::Full => i * self.size + j,
+            MatrixLayout::Condensed => {
+                let (i, j) = (i.min(j), i.max(j));
+                // Rows before i hold (n - 1) + (n - 2) + ... + (n - i) values.
+                i * (2 * self.size - i - 1) / 2 + (j - i - 1)
+            }
+        }
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> T {
+        if i == j {
+            return self.diagonal;
+        }
+        self.values[self.offset(i, j)]
+    }
+
+    /// Sets elements (i, j) and (j, i), `i != j`.
+    fn set(&mut self, i: usize, j: usize, value: T) {
+        let offset = self.offset(i, j);
+        self.values[offset] = value;
+        if self.layout == MatrixLayout::Full {
+            self.values[j * self.size + i] = value;
+        }
+    }
+
+    /// Same values in the other layout.
+    pub fn with_layout(&self, layout: MatrixLayout) -> Self {
+        ResidueMatrix::from_pairs(self.size, layout, self.diagonal, |i, j| self.get(i, j))
+    }
+
+    pub fn map<U: Copy>(&self, f: impl Fn(T) -> U) -> ResidueMatrix<U> {
+        ResidueMatrix {
+            size: self.size,
+            layout: self.layout,
+            values: self.values.iter().map(|&v| f(v)).collect(),
+            diagonal: f(self.diagonal),
+        }
+    }
+
+    pub fn to_array(&self) -> Array2<T> {
+        Array2::from_shape_fn((self.size, self.size), |(i, j)| self.get(i, j))
+    }
+}
+
+impl ResidueMatrix<f32> {
+    /// Binary contact map, 1 where the distance is at most `cutoff`.
+    pub fn contact_map(&self, cutoff: f32) -> ResidueMatrix<u8> {
+        self.map(|d| (d <= cutoff) as u8)
+    }
+
+    /// Half precision copy, a quarter of the size of the full f32 matrix once condensed.
+    pub fn to_f16(&self) -> ResidueMatrix<f16> {
+        self.map(f16::from_f32)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DistanceMatrix<'a> {
+    /// Polymer residues having at least one of the selected atoms, chain after chain
+    pub residues: Vec<ResidueView<'a>>,
+    /// Shortest distance between the selected atoms of each pair of residues, in A
+    pub distances: ResidueMatrix<f32>,
+}
+
+fn site_distance(a: &ContactSite, b: &ContactSite) -> f32 {
+    a.atoms
+        .iter()
+        .flat_map(|x| b.atoms.iter().map(move |y| (x.x - y.x).powi(2) + (x.y - y.y).powi(2) + (x.z - y.z).powi(2)))
+        .fold(f32::INFINITY, f32::min)
+        .sqrt()
+}
+
+/// Residue distance matrix of a model over all its chains.
+///
+/// With [`ContactAtoms::HeavyAtoms`] each entry is the closest approach of the two residues.
+pub fn distance_matrix<'a>(model: &ModelView<'a>, atoms: ContactAtoms, layout: MatrixLayout) -> DistanceMatrix<'a> {
+    let (residues, sites) = contact_sites(model, atoms);
+    let distances = ResidueMatrix::from_pairs(sites.len(), layout, 0.0, |i, j| site_distance(&sites[i], &sites[j]));
+    DistanceMatrix { residues, distances }
+}
+
+#[derive(Debug, Clone)]
+pub struct ContactMap<'a> {
+    /// Polymer residues having at least one of the selected atoms, chain after chain
+    pub residues: Vec<ResidueView<'a>>,
+    /// 1 for residues with atoms at most the cutoff apart, including the diagonal
+    pub contacts: ResidueMatrix<u8>,
+}
+
+/// Residue contact map of a model over all its chains.
+///
+/// Same values as [`ResidueMatrix::contact_map`] on the [`distance_matrix`], from a
+/// neighbour search rather than every pairwise distance.
+pub fn contact_map<'a>(model: &ModelView<'a>, atoms: ContactAtoms, cutoff: f32, layout: MatrixLayout) -> ContactMap<'a> {
+    let (residues, sites) = contact_sites(model, atoms);
+    let mut contacts = ResidueMatrix::from_pairs(sites.len(), layout, 1, |_, _| 0);
+    for (i, j) in sites_within(&sites, cutoff) {
+        contacts.set(i, j, 1);
+    }
+    ContactMap { residues, contacts }
+}
@@ -0,0 +1,74 @@
+use pdb_io::AtomCoordinate;
+use rstar::{PointDistance, RTree, RTreeObject, AABB};
+
+type Point = [f32; 3];
+
+/// A point of the index, remembering its position in the input.
+#[derive(Debug, Clone, Copy)]
+struct IndexedPoint {
+    position: Point,
+    index: usize,
+}
+
+impl RTreeObject for IndexedPoint {
+    type Envelope = AABB<Point>;
+
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_point(self.position)
+    }
+}
+
+impl PointDistance for IndexedPoint {
+    fn distance_2(&self, point: &Point) -> f32 {
+        let p = self.position;
+        (p[0] - point[0]).powi(2) + (p[1] - point[1]).powi(2) + (p[2] - point[2]).powi(2)
+    }
+}
+
+/// Fixed-radius neighbour search over a set of points, backed by an R*-tree.
+///
+/// Shared by everything looking for nearby atoms or residues (contacts, contact maps,
+/// hydrogen bonds), so each only pays for the pairs that can actually interact.
+#[derive(Debug, Clone)]
+pub struct NeighbourSearch {
+    tree: RTree<IndexedPoint>,
+    points: Vec<Point>,
+}
+
+impl NeighbourSearch {
+    pub fn new(points: Vec<Point>) -> Self {
+        let tree = RTree::bulk_load(points.iter().enumerate().map(|(index, &position)| IndexedPoint { position, index }).collect());
+        NeighbourSearch { tree, points }
+    }
+
+    pub fn from_atoms(atoms: &[&AtomCoordinate]) -> Self {
+        NeighbourSearch::new(atoms.iter().map(|a| [a.x, a.y, a.z]).collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Indices of the points at most `radius` from `centre`, with their squared distances, in no particular order.
+    pub fn within(&self, centre: Point, radius: f32) -> impl Iterator<Item = (usize, f32)> + '_ {
+        self.tree
+            .locate_within_distance(centre, radius * radius)
+            .map(move |point| (point.index, point.distance_2(&centre)))
+    }
+
+    /// Every pair of points `i < j` at most `radius` apart, sorted.
+    pub fn pairs_within(&self, radius: f32) -> Vec<(usize, usize)> {
+        let mut pairs: Vec<(usize, usize)> = self
+            .points
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &point)| self.within(point, radius).filter(move |&(j, _)| j > i).map(move |(j, _)| (i, j)))
+            .collect();
+        pairs.sort_unstable();
+        pairs
+    }
+}
@@ -0,0 +1,87 @@
+use std::ops::Range;
+
+use pdb_io::PredictedAlignedError;
+
+use crate::domains::Domain;
+
+/// PAE under which a residue pair counts as confidently placed, in A.
+pub const CONFIDENT_PAE: f32 = 5.0;
+
+/// Summary of a predicted aligned error matrix, over pairs of distinct residues.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PaeSummary {
+    /// Mean PAE, in A
+    pub mean: f32,
+    /// Fraction of pairs under [`CONFIDENT_PAE`], between 0 and 1
+    pub fraction_confident: f32,
+    /// Mean PAE between residues of the same domain, `None` without such pairs
+    pub intra_domain: Option<f32>,
+    /// Mean PAE between residues of different domains, `None` with fewer than two domains
+    pub inter_domain: Option<f32>,
+}
+
+fn mean(sum: f64, count: usize) -> Option<f32> {
+    (count > 0).then(|| (sum / count as f64) as f32)
+}
+
+/// Summarises a PAE matrix, splitting pairs by the domains given as residue index ranges.
+///
+/// Indices follow the PAE rows; residues outside every domain, and domains reaching
+/// past the matrix, only count towards the overall mean and fraction.
+pub fn pae_summary(pae: &PredictedAlignedError, domains: &[Range<usize>]) -> PaeSummary {
+    let mut domain_of = vec![None; pae.size];
+    for (d, range) in domains.iter().enumerate() {
+        if range.end <= pae.size {
+            domain_of[range.clone()].fill(Some(d));
+        }
+    }
+    summary_by_label(pae, &domain_of)
+}
+
+/// [`pae_summary`] over parsed domains, whose ranges all count towards the same domain.
+pub fn pae_domain_summary(pae: &PredictedAlignedError, domains: &[Domain]) -> PaeSummary {
+    let mut domain_of = vec![None; pae.size];
+    for (d, domain) in domains.iter().enumerate() {
+        if domain.ranges.iter().all(|range| range.end <= pae.size) {
+            for range in &domain.ranges {
+                domain_of[range.clone()].fill(Some(d));
+            }
+        }
+    }
+    summary_by_label(pae, &domain_of)
+}
+
+fn summary_by_label(pae: &PredictedAlignedError, domain_of: &[Option<usize>]) -> PaeSummary {
+    let n = pae.size;
+    let (mut sum, mut count, mut confident) = (0.0f64, 0usize, 0usize);
+    let (mut intra, mut intra_count, mut inter, mut inter_count) = (0.0f64, 0usize, 0.0f64, 0usize);
+    for i in 0..n {
+        for j in 0..n {
+            if i == j {
+                continue;
+            }
+            let value = pae.get(i, j);
+            sum += value as f64;
+            count += 1;
+            confident += (value < CONFIDENT_PAE) as usize;
+            match (domain_of[i], domain_of[j]) {
+                (Some(a), Some(b)) if a == b => {
+                    intra += value as f64;
+                    intra_count += 1;
+                }
+                (Some(_), Some(_)) => {
+                    inter += value as f64;
+                    inter_count += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    PaeSummary {
+        mean: mean(sum, count).unwrap_or(0.0),
+        fraction_confident: if count > 0 { confident as f32 / count as f32 } else { 0.0 },
+        intra_domain: mean(intra, intra_count),
+        inter_domain: mean(inter, inter_count),
+    }
+}
@@ -0,0 +1,350 @@
+use pdb_io::{ModelView, ResidueView};
+
+use crate::dihedrals::dihedral;
+
+/// Largest deviation of each chi angle from a rotamer centre still counted as that rotamer, in degrees.
+pub const ROTAMER_TOLERANCE: f32 = 40.0;
+
+/// Rotamers of a residue type, as chi1, chi2... centres in degrees.
+enum Rotamers {
+    Listed(&'static [(&'static str, &'static [f32])]),
+    /// Any combination of the per-chi centres, named after their p / t / m letters
+    Combinations(&'static [&'static [f32]]),
+}
+
+struct SideChain {
+    residues: &'static [&'static str],
+    /// Atoms defining chi1, chi2...
+    chi: &'static [[&'static str; 4]],
+    /// Chi angle of a symmetric terminal group (carboxylate, phenyl ring), defined modulo 180
+    symmetric_chi: Option<usize>,
+    rotamers: Rotamers,
+}
+
+const CHI1_G: [&str; 4] = ["N", "CA", "CB", "CG"];
+const CHI2_D: [&str; 4] = ["CA", "CB", "CG", "CD"];
+const CHI2_D1: [&str; 4] = ["CA", "CB", "CG", "CD1"];
+const CHI2_OD1: [&str; 4] = ["CA", "CB", "CG", "OD1"];
+const CHI3_OE1: [&str; 4] = ["CB", "CG", "CD", "OE1"];
+
+const STAGGERED: &[f32] = &[62.0, -177.0, -65.0];
+
+// Modal values of the Lovell et al. (2000) penultimate rotamer library.
+const SIDE_CHAINS: &[SideChain] = &[
+    SideChain {
+        residues: &["SER"],
+        chi: &[["N", "CA", "CB", "OG"]],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(&[("p", &[62.0]), ("t", &[-177.0]), ("m", &[-65.0])]),
+    },
+    SideChain {
+        residues: &["CYS"],
+        chi: &[["N", "CA", "CB", "SG"]],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(&[("p", &[62.0]), ("t", &[-177.0]), ("m", &[-65.0])]),
+    },
+    SideChain {
+        residues: &["THR"],
+        chi: &[["N", "CA", "CB", "OG1"]],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(&[("p", &[59.0]), ("t", &[-171.0]), ("m", &[-61.0])]),
+    },
+    SideChain {
+        residues: &["VAL"],
+        chi: &[["N", "CA", "CB", "CG1"]],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(&[("p", &[63.0]), ("t", &[175.0]), ("m", &[-60.0])]),
+    },
+    SideChain {
+        residues: &["PRO"],
+        chi: &[CHI1_G, CHI2_D],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(&[("Cg_endo", &[30.0]), ("Cg_exo", &[-30.0])]),
+    },
+    SideChain {
+        residues: &["ILE"],
+        chi: &[["N", "CA", "CB", "CG1"], ["CA", "CB", "CG1", "CD1"]],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(&[
+            ("pp", &[62.0, 100.0]),
+            ("pt", &[62.0, 170.0]),
+            ("tp", &[-177.0, 66.0]),
+            ("tt", &[-177.0, 165.0]),
+            ("mp", &[-65.0, 100.0]),
+            ("mt", &[-65.0, 170.0]),
+            ("mm", &[-57.0, -60.0]),
+        ]),
+    },
+    SideChain {
+        residues: &["LEU"],
+        chi: &[CHI1_G, CHI2_D1],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(&[
+            ("pp", &[62.0, 80.0]),
+            ("tp", &[-177.0, 65.0]),
+            ("tt", &[-172.0, 145.0]),
+            ("mp", &[-85.0, 65.0]),
+            ("mt", &[-65.0, 175.0]),
+        ]),
+    },
+    SideChain {
+        residues: &["ASP"],
+        chi: &[CHI1_G, CHI2_OD1],
+        symmetric_chi: Some(1),
+        rotamers: Rotamers::Listed(&[
+            ("p-10", &[62.0, -10.0]),
+            ("p30", &[62.0, 30.0]),
+            ("t0", &[-177.0, 0.0]),
+            ("t70", &[-177.0, 65.0]),
+            ("m-20", &[-70.0, -15.0]),
+        ]),
+    },
+    SideChain {
+        residues: &["ASN"],
+        chi: &[CHI1_G, CHI2_OD1],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(&[
+            ("p-10", &[62.0, -10.0]),
+            ("p30", &[62.0, 30.0]),
+            ("t-20", &[-174.0, -20.0]),
+            ("t30", &[-177.0, 30.0]),
+            ("m-20", &[-65.0, -20.0]),
+            ("m-80", &[-65.0, -75.0]),
+            ("m120", &[-65.0, 120.0]),
+        ]),
+    },
+    SideChain {
+        residues: &["HIS"],
+        chi: &[CHI1_G, ["CA", "CB", "CG", "ND1"]],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(&[
+            ("p-80", &[62.0, -75.0]),
+            ("p80", &[62.0, 80.0]),
+            ("t-160", &[-177.0, -165.0]),
+            ("t-80", &[-177.0, -80.0]),
+            ("t60", &[-177.0, 60.0]),
+            ("m-70", &[-65.0, -70.0]),
+            ("m170", &[-65.0, 165.0]),
+            ("m80", &[-65.0, 80.0]),
+        ]),
+    },
+    SideChain {
+        residues: &["PHE", "TYR"],
+        chi: &[CHI1_G, CHI2_D1],
+        symmetric_chi: Some(1),
+        rotamers: Rotamers::Listed(&[
+            ("p90", &[62.0, 90.0]),
+            ("t80", &[-177.0, 80.0]),
+            ("m-85", &[-65.0, -85.0]),
+            ("m-30", &[-65.0, -30.0]),
+        ]),
+    },
+    SideChain {
+        residues: &["TRP"],
+        chi: &[CHI1_G, CHI2_D1],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(&[
+            ("p-90", &[62.0, -90.0]),
+            ("p90", &[62.0, 90.0]),
+            ("t-105", &[-177.0, -105.0]),
+            ("t90", &[-177.0, 90.0]),
+            ("m-90", &[-65.0, -90.0]),
+            ("m0", &[-65.0, -5.0]),
+            ("m95", &[-65.0, 95.0]),
+        ]),
+    },
+    SideChain {
+        residues: &["MET"],
+        chi: &[CHI1_G, ["CA", "CB", "CG", "SD"], ["CB", "CG", "SD", "CE"]],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(METHIONINE),
+    },
+    SideChain {
+        residues: &["MSE"],
+        chi: &[CHI1_G, ["CA", "CB", "CG", "SE"], ["CB", "CG", "SE", "CE"]],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(METHIONINE),
+    },
+    SideChain {
+        residues: &["GLU"],
+        chi: &[CHI1_G, CHI2_D, CHI3_OE1],
+        symmetric_chi: Some(2),
+        rotamers: Rotamers::Listed(&[
+            ("pt-20", &[62.0, 180.0, -20.0]),
+            ("pm0", &[70.0, -80.0, 0.0]),
+            ("tp10", &[-177.0, 65.0, 10.0]),
+            ("tt0", &[-177.0, 180.0, 0.0]),
+            ("tm-20", &[-177.0, -80.0, -25.0]),
+            ("mp0", &[-65.0, 85.0, 0.0]),
+            ("mt-10", &[-67.0, 180.0, -10.0]),
+            ("mm-40", &[-65.0, -65.0, -40.0]),
+        ]),
+    },
+    SideChain {
+        residues: &["GLN"],
+        chi: &[CHI1_G, CHI2_D, CHI3_OE1],
+        symmetric_chi: None,
+        rotamers: Rotamers::Listed(&[
+            ("pt20", &[62.0, 180.0, 20.0]),
+            ("pm0", &[70.0, -75.0, 0.0]),
+            ("tp-100", &[-177.0, 65.0, -100.0]),
+            ("tp60", &[-177.0, 65.0, 60.0]),
+            ("tt0", &[-177.0, 180.0, 0.0]),
+            ("mp0", &[-65.0, 85.0, 0.0]),
+            ("mt-30", &[-67.0, 180.0, -25.0]),
+            ("mm-40", &[-65.0, -65.0, -40.0]),
+            ("mm100", &[-65.0, -65.0, 100.0]),
+        ]),
+    },
+    SideChain {
+        residues: &["LYS"],
+        chi: &[CHI1_G, CHI2_D, ["CB", "CG", "CD", "CE"], ["CG", "CD", "CE", "NZ"]],
+        symmetric_chi: None,
+        rotamers: Rotamers::Combinations(&[STAGGERED, &[65.0, 180.0, -65.0], &[65.0, 180.0, -65.0], &[65.0, 180.0, -65.0]]),
+    },
+    SideChain {
+        residues: &["ARG"],
+        chi: &[CHI1_G, CHI2_D, ["CB", "CG", "CD", "NE"], ["CG", "CD", "NE", "CZ"]],
+        symmetric_chi: None,
+        rotamers: Rotamers::Combinations(&[STAGGERED, &[65.0, 180.0, -65.0], &[65.0, 180.0, -65.0], &[85.0, 180.0, -85.0]]),
+    },
+];
+
+const METHIONINE: &[(&str, &[f32])] = &[
+    ("ptp", &[62.0, 180.0, 75.0]),
+    ("ptm", &[62.0, 180.0, -75.0]),
+    ("tpp", &[-177.0, 65.0, 75.0]),
+    ("tpt", &[-177.0, 65.0, 180.0]),
+    ("ttp", &[-177.0, 180.0, 75.0]),
+    ("ttt", &[-177.0, 180.0, 180.0]),
+    ("ttm", &[-177.0, 180.0, -75.0]),
+    ("mtp", &[-67.0, 180.0, 75.0]),
+    ("mtt", &[-67.0, 180.0, 180.0]),
+    ("mtm", &[-67.0, 180.0, -75.0]),
+    ("mmp", &[-65.0, -65.0, 103.0]),
+    ("mmt", &[-65.0, -65.0, 180.0]),
+    ("mmm", &[-65.0, -65.0, -70.0]),
+];
+
+fn side_chain(residue_name: &str) -> Option<&'static SideChain> {
+    SIDE_CHAINS.iter().find(|side_chain| side_chain.residues.contains(&residue_name))
+}
+
+/// Absolute difference between two angles in degrees, in [0, 180].
+fn angle_difference(a: f32, b: f32) -> f32 {
+    let d = (a - b).rem_euclid(360.0);
+    d.min(360.0 - d)
+}
+
+impl SideChain {
+    fn chi_fits(&self, index: usize, chi: f32, centre: f32) -> bool {
+        let fits = |chi: f32| angle_difference(chi, centre) <= ROTAMER_TOLERANCE;
+        fits(chi) || (self.symmetric_chi == Some(index) && fits(chi + 180.0))
+    }
+
+    fn fit(&self, chi: &[f32]) -> RotamerFit {
+        let fits = |centres: &[f32]| centres.iter().zip(chi).enumerate().all(|(i, (&centre, &chi))| self.chi_fits(i, chi, centre));
+
+        let name = match &self.rotamers {
+            Rotamers::Listed(rotamers) => rotamers.iter().find(|(_, centres)| fits(centres)).map(|(name, _)| name.to_string()),
+            Rotamers::Combinations(centres) => {
+                let closest: Vec<f32> = centres
+                    .iter()
+                    .zip(chi)
+                    .map(|(options, &chi)| {
+                        let difference = |centre: &&f32| angle_difference(chi, **centre);
+                        *options.iter().min_by(|a, b| difference(a).total_cmp(&difference(b))).expect("centres are listed")
+                    })
+                    .collect();
+                fits(&closest).then(|| closest.iter().map(|&centre| staggered_letter(centre)).collect())
+            }
+        };
+
+        name.map_or(RotamerFit::Outlier, RotamerFit::Rotamer)
+    }
+
+    /// Number of chi angles the rotamers are defined on.
+    fn rotamer_chi_count(&self) -> usize {
+        match &self.rotamers {
+            Rotamers::Listed(rotamers) => rotamers[0].1.len(),
+            Rotamers::Combinations(centres) => centres.len(),
+        }
+    }
+}
+
+fn staggered_letter(centre: f32) -> char {
+    if centre.abs() > 120.0 {
+        't'
+    } else if centre > 0.0 {
+        'p'
+    } else {
+        'm'
+    }
+}
+
+/// Rotamer of a side chain: its name in the library, or an outlier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RotamerFit {
+    Rotamer(String),
+    Outlier,
+}
+
+/// Side-chain dihedrals of one residue; chi angles with a missing atom, or beyond the residue's side chain, are `None`.
+#[derive(Debug, Clone, Copy)]
+pub struct SideChainDihedrals<'a> {
+    pub residue: ResidueView<'a>,
+    /// chi1 to chi4, in degrees
+    pub chi: [Option<f32>; 4],
+}
+
+impl<'a> SideChainDihedrals<'a> {
+    pub fn new(residue: ResidueView<'a>) -> Self {
+        let mut chi = [None; 4];
+        if let Some(side_chain) = side_chain(residue.name()) {
+            for (angle, names) in chi.iter_mut().zip(side_chain.chi) {
+                let atoms: Option<Vec<_>> = names.iter().map(|name| residue.atom(name)).collect();
+                *angle = atoms.map(|a| dihedral(a[0], a[1], a[2], a[3]));
+            }
+        }
+        SideChainDihedrals { residue, chi }
+    }
+
+    /// Closest rotamer of the built-in library, for residues with rotamers and all the chi angles they need.
+    ///
+    /// Each chi angle must lie within [`ROTAMER_TOLERANCE`] of the rotamer centre.
+    /// Lysine and arginine accept any combination of staggered chi angles.
+    pub fn rotamer(&self) -> Option<RotamerFit> {
+        let side_chain = side_chain(self.residue.name())?;
+        let chi: Option<Vec<f32>> = self.chi[..side_chain.rotamer_chi_count()].iter().copied().collect();
+        Some(side_chain.fit(&chi?))
+    }
+}
+
+/// Side-chain dihedrals of every polymer residue with at least one chi angle in its side chain (not ALA or GLY).
+pub fn side_chain_dihedrals<'a>(model: &ModelView<'a>) -> Vec<SideChainDihedrals<'a>> {
+    model
+        .chains
+        .iter()
+        .flat_map(|chain| chain.polymer_residues())
+        .filter(|residue| side_chain(residue.name()).is_some())
+        .map(|residue| SideChainDihedrals::new(*residue))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RotamerSummary {
+    /// Fraction of evaluated residues off every rotamer, between 0 and 1
+    pub outlier: f32,
+    /// Residues with a complete side chain
+    pub evaluated: usize,
+}
+
+pub fn rotamer_summary(side_chains: &[SideChainDihedrals]) -> RotamerSummary {
+    let fits: Vec<RotamerFit> = side_chains.iter().filter_map(SideChainDihedrals::rotamer).collect();
+    let n = fits.len();
+    if n == 0 {
+        return RotamerSummary::default();
+    }
+    let outliers = fits.iter().filter(|&fit| *fit == RotamerFit::Outlier).count();
+    RotamerSummary { outlier: outliers as f32 / n as f32, evaluated: n }
+}
@@ -0,0 +1,153 @@
+use ndarray::{Array1, Array2};
+use pdb_io::AtomCoordinate;
+
+/// Gyration tensor of a set of atoms, with its eigen decomposition.
+#[derive(Debug, Clone)]
+pub struct GyrationTensor {
+    /// Mean outer product of the positions relative to the centroid, 3 x 3
+    pub tensor: Array2<f32>,
+    /// Eigenvalues in ascending order, in A^2
+    pub eigenvalues: [f32; 3],
+    /// Principal axes as columns, in the order of `eigenvalues`
+    pub axes: Array2<f32>,
+    pub centroid: [f32; 3],
+}
+
+/// Shape descriptors derived from the gyration tensor eigenvalues l1 <= l2 <= l3.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ShapeDescriptors {
+    pub eigenvalues: [f32; 3],
+    /// l3 - (l1 + l2) / 2, 0 for spherically symmetric shapes
+    pub asphericity: f32,
+    /// l2 - l1, 0 for cylindrically symmetric shapes
+    pub acylindricity: f32,
+    /// Relative shape anisotropy kappa^2, from 0 (sphere) to 1 (rod)
+    pub anisotropy: f32,
+}
+
+impl GyrationTensor {
+    pub fn radius_of_gyration(&self) -> f32 {
+        self.eigenvalues.iter().sum::<f32>().sqrt()
+    }
+
+    pub fn shape(&self) -> ShapeDescriptors {
+        let [l1, l2, l3] = self.eigenvalues;
+        let asphericity = l3 - (l1 + l2) / 2.0;
+        let acylindricity = l2 - l1;
+        let rg2 = l1 + l2 + l3;
+        let anisotropy = if rg2 > 0.0 {
+            (asphericity * asphericity + 0.75 * acylindricity * acylindricity) / (rg2 * rg2)
+        } else {
+            0.0
+        };
+        ShapeDescriptors { eigenvalues: self.eigenvalues, asphericity, acylindricity, anisotropy }
+    }
+}
+
+/// Gyration tensor of the atoms, `None` without atoms.
+///
+/// Its trace is the squared radius of gyration, so both agree on the same atoms.
+pub fn gyration_tensor(ca_atoms: &[&AtomCoordinate]) -> Option<GyrationTensor> {
+    let n = ca_atoms.len();
+    if n == 0 {
+        return None;
+    }
+
+    let positions = Array2::from_shape_fn((n, 3), |(i, k)| {
+        let atom = ca_atoms[i];
+        [atom.x, atom.y, atom.z][k] as f64
+    });
+    let centroid: Array1<f64> = positions.sum_axis(ndarray::Axis(0)) / n as f64;
+    let centered = &positions - &centroid;
+    let tensor = centered.t().dot(&centered) / n as f64;
+
+    let (eigenvalues, axes) = symmetric_eigen(&tensor);
+    Some(GyrationTensor {
+        tensor: tensor.mapv(|v| v as f32),
+        // Rounding can leave slightly negative values for flat or linear sets.
+        eigenvalues: [0, 1, 2].map(|k| eigenvalues[k].max(0.0) as f32),
+        axes: axes.mapv(|v| v as f32),
+        centroid: [0, 1, 2].map(|k| centroid[k] as f32),
+    })
+}
+
+/// Eigenvalues (ascending) and eigenvectors (columns) of a symmetric 3 x 3 matrix, by cyclic Jacobi rotations.
+pub(crate) fn symmetric_eigen(matrix: &Array2<f64>) -> ([f64; 3], Array2<f64>) {
+    let mut a = matrix.clone();
+    let mut v = Array2::<f64>::eye(3);
+
+    for _ in 0..50 {
+        let off_diagonal = a[[0, 1]].powi(2) + a[[0, 2]].powi(2) + a[[1, 2]].powi(2);
+        if off_diagonal <= 1e-24 * a.iter().map(|x| x * x).sum::<f64>() {
+            break;
+        }
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if a[[p, q]] == 0.0 {
+                continue;
+            }
+            // Rotation zeroing a[p][q]
+            let theta = (a[[q, q]] - a[[p, p]]) / (2.0 * a[[p, q]]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+
+            for k in 0..3 {
+                let (akp, akq) = (a[[k, p]], a[[k, q]]);
+                a[[k, p]] = c * akp - s * akq;
+                a[[k, q]] = s * akp + c * akq;
+            }
+            for k in 0..3 {
+                let (apk, aqk) = (a[[p, k]], a[[q, k]]);
+                a[[p, k]] = c * apk - s * aqk;
+                a[[q, k]] = s * apk + c * aqk;
+            }
+            for k in 0..3 {
+                let (vkp, vkq) = (v[[k, p]], v[[k, q]]);
+                v[[k, p]] = c * vkp - s * vkq;
+                v[[k, q]] = s * vkp + c * vkq;
+            }
+        }
+    }
+
+    let mut order = [0, 1, 2];
+    order.sort_by(|&i, &j| a[[i, i]].total_cmp(&a[[j, j]]));
+    let eigenvalues = order.map(|i| a[[i, i]]);
+    let vectors = Array2::from_shape_fn((3, 3), |(k, column)| v[[k, order[column]]]);
+    (eigenvalues, vectors)
+}
+
+/// Bounding box aligned on the principal axes of the gyration tensor, independent of the input frame.
+#[derive(Debug, Clone)]
+pub struct OrientedBoundingBox {
+    pub center: [f32; 3],
+    /// Box axes as columns, from the smallest to the largest principal moment
+    pub axes: Array2<f32>,
+    /// Edge lengths along each axis, in A
+    pub lengths: [f32; 3],
+}
+
+impl OrientedBoundingBox {
+    pub fn volume(&self) -> f32 {
+        self.lengths.iter().product()
+    }
+}
+
+/// Smallest box containing the atoms along their principal axes, `None` without atoms.
+pub fn oriented_bounding_box(atoms: &[&AtomCoordinate]) -> Option<OrientedBoundingBox> {
+    let tensor = gyration_tensor(atoms)?;
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for atom in atoms {
+        let relative = [atom.x - tensor.centroid[0], atom.y - tensor.centroid[1], atom.z - tensor.centroid[2]];
+        for k in 0..3 {
+            let projection: f32 = (0..3).map(|i| relative[i] * tensor.axes[[i, k]]).sum();
+            min[k] = min[k].min(projection);
+            max[k] = max[k].max(projection);
+        }
+    }
+
+    let center = [0, 1, 2].map(|i| {
+        tensor.centroid[i] + (0..3).map(|k| (min[k] + max[k]) / 2.0 * tensor.axes[[i, k]]).sum::<f32>()
+    });
+    Some(OrientedBoundingBox { center, lengths: [0, 1, 2].map(|k| max[k] - min[k]), axes: tensor.axes })
+}
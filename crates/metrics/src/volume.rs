@@ -0,0 +1,182 @@
+use pdb_io::AtomCoordinate;
+
+/// Van der Waals radius used for elements missing from the table, in A.
+pub const DEFAULT_VDW_RADIUS: f32 = 1.8;
+
+/// Grid parameters of [`molecular_volume`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumeOptions {
+    /// Edge of a grid cell, in A; the error shrinks with the cube of the spacing, so does the memory
+    pub grid_spacing: f32,
+    /// Radius of the solvent probe, in A
+    pub probe_radius: f32,
+}
+
+impl Default for VolumeOptions {
+    fn default() -> Self {
+        VolumeOptions { grid_spacing: 0.5, probe_radius: 1.4 }
+    }
+}
+
+/// Volumes of a set of atoms, in A^3.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MolecularVolume {
+    /// Union of the van der Waals spheres
+    pub van_der_waals: f32,
+    /// Solvent-excluded volume: the van der Waals volume plus the crevices and cavities the probe cannot enter
+    pub molecular: f32,
+}
+
+impl MolecularVolume {
+    /// Van der Waals volume over molecular volume, between 0 and 1; 0 without atoms.
+    pub fn packing_density(&self) -> f32 {
+        if self.molecular > 0.0 {
+            self.van_der_waals / self.molecular
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Van der Waals radius of an atom, from its element.
+pub fn van_der_waals_radius(atom: &AtomCoordinate) -> f32 {
+    sasa::van_der_waals_radius(&atom.element).unwrap_or(DEFAULT_VDW_RADIUS)
+}
+
+/// Probe centre reachable from the bulk solvent
+const PROBE: u8 = 1;
+/// Covered by a solvent ball
+const SOLVENT: u8 = 2;
+
+const FACE_STEPS: &[(isize, isize, isize)] = &[(-1, 0, 0), (1, 0, 0), (0, -1, 0), (0, 1, 0), (0, 0, -1), (0, 0, 1)];
+
+/// Geometry of the sampling grid; cell values live in arrays indexed by [`Lattice::index`].
+#[derive(Clone, Copy)]
+struct Lattice {
+    origin: [f32; 3],
+    spacing: f32,
+    dims: [usize; 3],
+}
+
+impl Lattice {
+    fn size(&self) -> usize {
+        self.dims.iter().product()
+    }
+
+    fn index(&self, i: usize, j: usize, k: usize) -> usize {
+        (i * self.dims[1] + j) * self.dims[2] + k
+    }
+
+    fn position(&self, i: usize, j: usize, k: usize) -> [f32; 3] {
+        let cell = [i, j, k];
+        [0, 1, 2].map(|axis| self.origin[axis] + cell[axis] as f32 * self.spacing)
+    }
+
+    /// Calls `visit` with the index and squared distance of every cell within `radius` of `centre`.
+    fn for_each_in_ball(&self, centre: [f32; 3], radius: f32, mut visit: impl FnMut(usize, f32)) {
+        let range = |axis: usize| {
+            let low = ((centre[axis] - radius - self.origin[axis]) / self.spacing).floor().max(0.0) as usize;
+            let high = ((centre[axis] + radius - self.origin[axis]) / self.spacing).ceil() as usize;
+            low..(high + 1).min(self.dims[axis])
+        };
+        let [range_i, range_j, range_k] = [0, 1, 2].map(range);
+        let offset = |axis: usize, n: usize| self.origin[axis] + n as f32 * self.spacing - centre[axis];
+        let radius2 = radius * radius;
+        for i in range_i {
+            let di2 = offset(0, i).powi(2);
+            for j in range_j.clone() {
+                let dij2 = di2 + offset(1, j).powi(2);
+                if dij2 > radius2 {
+                    continue;
+                }
+                let row = self.index(i, j, 0);
+                for k in range_k.clone() {
+                    let d2 = dij2 + offset(2, k).powi(2);
+                    if d2 <= radius2 {
+                        visit(row + k, d2);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Neighbours of a cell sharing a face with it.
+    fn neighbours(&self, i: usize, j: usize, k: usize) -> impl Iterator<Item = (usize, usize, usize)> {
+        let dims = self.dims;
+        FACE_STEPS.iter().filter_map(move |&(di, dj, dk)| {
+            let (i, j, k) = (i as isize + di, j as isize + dj, k as isize + dk);
+            let inside = (0..dims[0] as isize).contains(&i) && (0..dims[1] as isize).contains(&j) && (0..dims[2] as isize).contains(&k);
+            inside.then_some((i as usize, j as usize, k as usize))
+        })
+    }
+}
+
+/// Van der Waals and solvent-excluded volumes of the atoms, on a grid.
+///
+/// Probe centres are flood-filled from the box boundary, so cavities closed to the
+/// bulk solvent count as molecular volume: a hollow model gets a low packing density.
+/// Each probe centre on the accessible surface then clears the largest ball free of
+/// atoms around it, which keeps the surface accurate below the grid spacing.
+pub fn molecular_volume(atoms: &[&AtomCoordinate], options: &VolumeOptions) -> MolecularVolume {
+    if atoms.is_empty() {
+        return MolecularVolume::default();
+    }
+    let radii: Vec<f32> = atoms.iter().map(|&atom| van_der_waals_radius(atom)).collect();
+    let (spacing, probe) = (options.grid_spacing, options.probe_radius);
+
+    // Padding keeps a layer of free probe centres around the molecule for the flood fill.
+    let padding = radii.iter().fold(0.0f32, |m, &r| m.max(r)) + probe + 2.0 * spacing;
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for atom in atoms {
+        for (axis, value) in [atom.x, atom.y, atom.z].iter().enumerate() {
+            min[axis] = min[axis].min(*value);
+            max[axis] = max[axis].max(*value);
+        }
+    }
+    let origin = min.map(|m| m - padding);
+    let dims = [0, 1, 2].map(|axis| ((max[axis] - min[axis] + 2.0 * padding) / spacing).ceil() as usize + 1);
+    let grid = Lattice { origin, spacing, dims };
+    // Distance to the closest van der Waals surface, negative inside an atom; infinite far from every atom
+    let mut clearance = vec![f32::INFINITY; grid.size()];
+    let mut flags = vec![0u8; grid.size()];
+
+    // Surface probe centres lie within one cell of the accessible surface.
+    for (atom, &radius) in atoms.iter().zip(&radii) {
+        grid.for_each_in_ball([atom.x, atom.y, atom.z], radius + probe + 2.0 * spacing, |index, d2| {
+            clearance[index] = clearance[index].min(d2.sqrt() - radius);
+        });
+    }
+
+    // Probe centres connected to the corner of the box, which lies in the padding.
+    let mut stack = vec![(0, 0, 0)];
+    flags[0] |= PROBE;
+    let mut surface = Vec::new();
+    while let Some((i, j, k)) = stack.pop() {
+        let mut touches_surface = false;
+        for (ni, nj, nk) in grid.neighbours(i, j, k) {
+            let index = grid.index(ni, nj, nk);
+            if clearance[index] < probe {
+                touches_surface = true;
+            } else if flags[index] & PROBE == 0 {
+                flags[index] |= PROBE;
+                stack.push((ni, nj, nk));
+            }
+        }
+        if touches_surface {
+            surface.push((i, j, k));
+        }
+    }
+
+    // Only probes against the accessible surface can sweep into buried cells.
+    for (i, j, k) in surface {
+        let free_radius = clearance[grid.index(i, j, k)];
+        grid.for_each_in_ball(grid.position(i, j, k), free_radius, |index, _| flags[index] |= SOLVENT);
+    }
+
+    let cell_volume = spacing.powi(3);
+    MolecularVolume {
+        van_der_waals: clearance.iter().filter(|&&c| c <= 0.0).count() as f32 * cell_volume,
+        molecular: flags.iter().filter(|&&f| f & (PROBE | SOLVENT) == 0).count() as f32 * cell_volume,
+    }
+}
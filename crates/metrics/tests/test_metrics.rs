@@ -1,17 +1,68 @@
-use pdb_io::parse_pdb;
+use pdb_io::{parse_pdb, parse_pdb_str, AtomCoordinate, PredictedAlignedError};
 use metrics::{radius_of_gyration, 
     bounding_box_volume, 
     contact_order, 
     plddt_statistics, 
-    get_ca_atoms};
+    get_ca_atoms,
+    chain_breaks,
+    model_chain_breaks,
+    dssp,
+    backbone_dihedrals,
+    ramachandran_summary,
+    RamachandranRegion,
+    ResidueClass,
+    side_chain_dihedrals,
+    rotamer_summary,
+    RotamerFit,
+    gyration_tensor,
+    oriented_bounding_box,
+    convex_hull,
+    get_heavy_atoms,
+    molecular_volume,
+    van_der_waals_radius,
+    VolumeOptions,
+    contact_order_with,
+    residue_contacts,
+    ContactAtoms,
+    ContactDefinition,
+    ContactOrderOptions,
+    ShortProteinPolicy,
+    distance_matrix,
+    contact_map,
+    MatrixLayout,
+    NeighbourSearch,
+    chain_confidence,
+    confidence_profile,
+    SegmentationOptions,
+    confidence_statistics,
+    ConfidenceSource,
+    ConfidenceThresholds,
+    pae_summary,
+    parse_domains,
+    DomainMethod,
+    DomainOptions,
+    MAX_HBOND_ENERGY};
 //use metrics::sasa::{calc_sasa_from_parsed_pdb,SasaMode};
 
+/// CA trace of an ideal alpha helix (2.3 A radius, 1.5 A rise, 100 degrees per residue).
+fn ideal_helix_pdb(n_residues: i32) -> String {
+    (1..=n_residues)
+        .map(|i| {
+            let angle = (i as f32 * 100.0).to_radians();
+            format!(
+                "ATOM  {:>5}  CA  ALA A{:>4}    {:>8.3}{:>8.3}{:>8.3}  1.00{:>6.2}           C\n",
+                i, i, 2.3 * angle.cos(), 2.3 * angle.sin(), 1.5 * i as f32, 40.0 + i as f32 * 2.0
+            )
+        })
+        .collect()
+}
+
 #[test]
 fn test_metrics_basic() {
 
-    let pdb = parse_pdb("/store/EQUIPES/BIM/MEMBERS/simon.herman/MicroStruct/test.pdb").expect("File sadly not found ... ");
+    let pdb = parse_pdb_str(&ideal_helix_pdb(30)).expect("Failed to parse generated helix");
     
-    let ca_atoms = get_ca_atoms(&pdb);
+    let ca_atoms = get_ca_atoms(&pdb.models[0]);
     let rg = radius_of_gyration(&ca_atoms);
     let vol = bounding_box_volume(&ca_atoms);
     let co = contact_order(&ca_atoms);
@@ -20,11 +71,740 @@ fn test_metrics_basic() {
     //let sasa = calc_sasa_from_parsed_pdb(&pdb, 10, SasaMode::Full);
 
 
-    assert!(rg >= 0.0);
-    assert!(vol >= 0.0);
-    assert!(co >= 0.0);
+    assert_eq!(ca_atoms.len(), 30);
+    assert!(rg > 0.0);
+    assert!(vol > 0.0);
+    assert!(co > 0.0);
+    // pLDDT runs from 42 to 100 in steps of 2.
+    assert!((plddt.0 - 71.0).abs() < 1e-3);
 
     println!("Radius : {rg}, Volume : {vol}, Contact : {co}, pLDDT : {plddt:?}");
     
 
 }
+
+fn ca(chain_id: &str, residue_seq: i32, x: f32, y: f32) -> AtomCoordinate {
+    AtomCoordinate {
+        atom_name: "CA".to_string(),
+        alt_loc: None,
+        residue_name: "ALA".to_string(),
+        chain_id: chain_id.to_string(),
+        residue_seq,
+        insertion_code: None,
+        occupancy: 1.0,
+        bfactor: 80.0,
+        element: "C".to_string(),
+        is_hetatm: false,
+        x,
+        y,
+        z: 0.0,
+    }
+}
+
+#[test]
+fn test_contact_order_ignores_interchain_pairs() {
+
+    // Two parallel straight chains with the same numbering, 4 A apart.
+    let atoms: Vec<AtomCoordinate> = (1..=20)
+        .map(|i| ca("A", i, i as f32 * 3.8, 0.0))
+        .chain((1..=20).map(|i| ca("B", i, i as f32 * 3.8, 4.0)))
+        .collect();
+    let refs: Vec<&AtomCoordinate> = atoms.iter().collect();
+
+    // Per chain: 19 contacts at |i-j| = 1 and 18 at |i-j| = 2.
+    let expected = 2.0 * (19.0 + 36.0) / (40.0 * 74.0) * 100.0;
+    assert!((contact_order(&refs) - expected).abs() < 1e-4);
+}
+
+#[test]
+fn test_get_ca_atoms_per_model() {
+
+    let pdb = parse_pdb(concat!(env!("CARGO_MANIFEST_DIR"), "/../pdb_io/tests/data/nmr_ensemble.pdb")).unwrap();
+    assert_eq!(pdb.models.len(), 2);
+
+    for model in &pdb.models {
+        // ALA, MSE (HETATM), GLY and LYS; the calcium ion is left out.
+        let residues: Vec<&str> = get_ca_atoms(model).iter().map(|a| a.residue_name.as_str()).collect();
+        assert_eq!(residues, ["ALA", "MSE", "GLY", "LYS"]);
+    }
+}
+
+#[test]
+fn test_chain_breaks_from_numbering_and_distance() {
+
+    // CA-only chain numbered 2, 3, 4, 6, 7 with residue 5 missing; the water is ignored.
+    let pdb = parse_pdb(concat!(env!("CARGO_MANIFEST_DIR"), "/../pdb_io/tests/data/seqres_gaps.pdb")).unwrap();
+    let view = pdb.models[0].view();
+    let breaks = model_chain_breaks(&view);
+
+    assert_eq!(breaks.breaks.len(), 1);
+    let gap = &breaks.breaks[0];
+    assert_eq!((gap.before, gap.after), ((4, None), (6, None)));
+    assert_eq!(gap.numbering_step, 2);
+    assert!(gap.numbering);
+    assert!(gap.physical);
+    assert!((gap.distance.unwrap() - 7.6).abs() < 1e-3);
+
+    let segments: Vec<_> = breaks.segments.iter().map(|s| (s.first.0, s.last.0, s.len())).collect();
+    assert_eq!(segments, [(2, 4, 3), (6, 7, 2)]);
+    assert_eq!(breaks.longest_segment(), 3);
+
+    // Full backbone with continuous numbering, residue 6 pulled 5 A away from residue 5.
+    let text: String = (1..=10)
+        .flat_map(|i| {
+            let shift = if i >= 6 { 5.0 } else { 0.0 };
+            let x = 3.8 * i as f32 + shift;
+            vec![("N", x), ("CA", x + 1.46), ("C", x + 2.5)]
+                .into_iter()
+                .map(move |(name, x)| {
+                    format!("ATOM      1  {:<3} GLY A{:>4}    {:>8.3}   0.000   0.000  1.00 90.00           {}\n",
+                        name, i, x, &name[..1])
+                })
+        })
+        .collect();
+    let pdb = parse_pdb_str(&text).unwrap();
+    let view = pdb.models[0].view();
+    let breaks = chain_breaks(&view.chains[0]);
+    assert_eq!(breaks.breaks.len(), 1);
+    assert_eq!(breaks.breaks[0].numbering_step, 1);
+    assert!(!breaks.breaks[0].numbering);
+    assert!((breaks.breaks[0].distance.unwrap() - 6.3).abs() < 1e-3);
+    assert_eq!(breaks.segments.iter().map(|s| s.len()).collect::<Vec<_>>(), [5, 5]);
+}
+
+#[test]
+fn test_chain_breaks_from_backward_numbering() {
+
+    // Evenly spaced CA trace: 51, 52, 52A and 53 are continuous, 120 back to 1 and the
+    // two residues 2 on either side of a water are numbering breaks without any physical gap.
+    let residues = [(51, ' '), (52, ' '), (52, 'A'), (53, ' '), (120, ' '), (1, ' '), (2, ' '), (2, ' '), (3, ' ')];
+    let mut text: String = residues
+        .iter()
+        .enumerate()
+        .map(|(i, (number, insertion))| {
+            format!("ATOM      1  CA  GLY A{:>4}{}   {:>8.3}   0.000   0.000  1.00 90.00           C\n",
+                number, insertion, 3.8 * i as f32)
+        })
+        .collect();
+    let water = "HETATM    1  O   HOH A 201      26.600  10.000   0.000  1.00 90.00           O\n";
+    let second_two = text.match_indices("ATOM").nth(7).unwrap().0;
+    text.insert_str(second_two, water);
+    let pdb = parse_pdb_str(&text).unwrap();
+    let view = pdb.models[0].view();
+    let breaks = chain_breaks(&view.chains[0]);
+
+    let found: Vec<_> = breaks.breaks.iter().map(|b| (b.before, b.after, b.numbering_step)).collect();
+    assert_eq!(found, [((53, None), (120, None), 67), ((120, None), (1, None), -119), ((2, None), (2, None), 0)]);
+    assert!(breaks.breaks.iter().all(|b| b.numbering && !b.physical));
+    assert_eq!(breaks.segments.iter().map(|s| s.len()).collect::<Vec<_>>(), [4, 1, 2, 2]);
+}
+
+/// Places atom D from A, B, C given the C-D bond length, the B-C-D angle and the A-B-C-D torsion (degrees).
+fn place_atom(a: [f32; 3], b: [f32; 3], c: [f32; 3], length: f32, angle: f32, torsion: f32) -> [f32; 3] {
+    let sub = |u: [f32; 3], v: [f32; 3]| [u[0] - v[0], u[1] - v[1], u[2] - v[2]];
+    let cross = |u: [f32; 3], v: [f32; 3]| [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+    let unit = |u: [f32; 3]| {
+        let norm = (u[0] * u[0] + u[1] * u[1] + u[2] * u[2]).sqrt();
+        [u[0] / norm, u[1] / norm, u[2] / norm]
+    };
+
+    let (angle, torsion) = (angle.to_radians(), torsion.to_radians());
+    let bc = unit(sub(c, b));
+    let n = unit(cross(sub(b, a), bc));
+    let m = cross(n, bc);
+    let d = [-length * angle.cos(), length * angle.sin() * torsion.cos(), length * angle.sin() * torsion.sin()];
+    [
+        c[0] + bc[0] * d[0] + m[0] * d[1] + n[0] * d[2],
+        c[1] + bc[1] * d[0] + m[1] * d[1] + n[1] * d[2],
+        c[2] + bc[2] * d[0] + m[2] * d[1] + n[2] * d[2],
+    ]
+}
+
+/// PDB text of an alanine backbone (N, CA, C, O) built from (phi, psi) pairs with trans peptide bonds.
+fn backbone_pdb(torsions: &[(f32, f32)]) -> String {
+    let mut n = [0.0, 0.0, 0.0];
+    let mut ca = [1.458, 0.0, 0.0];
+    let mut c = place_atom([0.0, 1.0, 0.0], n, ca, 1.525, 111.2, -60.0);
+    let mut text = String::new();
+    let mut serial = 0;
+
+    for (i, &(phi, psi)) in torsions.iter().enumerate() {
+        if i > 0 {
+            let next_n = place_atom(n, ca, c, 1.329, 116.2, torsions[i - 1].1);
+            let next_ca = place_atom(ca, c, next_n, 1.458, 121.7, 180.0);
+            let next_c = place_atom(c, next_n, next_ca, 1.525, 111.2, phi);
+            n = next_n;
+            ca = next_ca;
+            c = next_c;
+        }
+        let o = place_atom(n, ca, c, 1.231, 120.5, psi + 180.0);
+        for &(name, p) in [("N", n), ("CA", ca), ("C", c), ("O", o)].iter() {
+            serial += 1;
+            text += &format!(
+                "ATOM  {:>5}  {:<3} ALA A{:>4}    {:>8.3}{:>8.3}{:>8.3}  1.00 90.00           {}\n",
+                serial, name, i + 1, p[0], p[1], p[2], &name[..1]
+            );
+        }
+    }
+    text
+}
+
+#[test]
+fn test_dssp_helix_and_hairpin() {
+
+    let helix = parse_pdb_str(&backbone_pdb(&[(-57.0, -47.0); 20])).unwrap();
+    let assignment = dssp(&helix.models[0].view());
+    assert_eq!(assignment.string(), "-HHHHHHHHHHHHHHHHHH-");
+    let fractions = assignment.fractions();
+    assert!((fractions.helix - 0.9).abs() < 1e-6);
+    assert_eq!(fractions.strand, 0.0);
+
+    // Two antiparallel strands joined by a two-residue turn.
+    let mut hairpin = vec![(-139.0, 135.0); 6];
+    hairpin.extend(vec![(60.0, -120.0), (-80.0, 0.0)]);
+    hairpin.extend(vec![(-139.0, 135.0); 6]);
+    let hairpin = parse_pdb_str(&backbone_pdb(&hairpin)).unwrap();
+    let assignment = dssp(&hairpin.models[0].view());
+    assert_eq!(assignment.string(), "-EEEEETTEEEEE-");
+    assert!((assignment.fractions().strand - 10.0 / 14.0).abs() < 1e-6);
+
+    // CA-only models have no backbone to assign.
+    let trace = parse_pdb_str(&ideal_helix_pdb(10)).unwrap();
+    assert!(dssp(&trace.models[0].view()).states.is_empty());
+}
+
+#[test]
+fn test_dssp_keeps_two_acceptors() {
+
+    // An alpha helix running into a 3-10 helix: the N-H of residue 9 bonds both O(5), i-4,
+    // and O(6), i-3, and a bond to O(8) of the preceding residue must not push one out.
+    let mut torsions = vec![(-57.0, -47.0); 8];
+    torsions.extend(vec![(-49.0, -26.0); 8]);
+    let pdb = parse_pdb_str(&backbone_pdb(&torsions)).unwrap();
+    let assignment = dssp(&pdb.models[0].view());
+    let mut partners: Vec<usize> = assignment.acceptors[9].iter().map(|&(acceptor, _)| acceptor).collect();
+    partners.sort_unstable();
+    assert_eq!(partners, [5, 6]);
+    assert!(assignment.acceptors[9].iter().all(|&(_, energy)| energy < MAX_HBOND_ENERGY));
+    assert_eq!(assignment.string(), "-HHHHHHHHGGGGGG-");
+
+    // Helices and strands alike only bond C=O further back than the preceding residue.
+    let mut hairpin = vec![(-139.0, 135.0); 6];
+    hairpin.extend(vec![(60.0, -120.0), (-80.0, 0.0)]);
+    hairpin.extend(vec![(-139.0, 135.0); 6]);
+    for torsions in [torsions, hairpin].iter() {
+        let pdb = parse_pdb_str(&backbone_pdb(torsions)).unwrap();
+        let assignment = dssp(&pdb.models[0].view());
+        for (donor, acceptors) in assignment.acceptors.iter().enumerate() {
+            assert!(acceptors.iter().all(|&(acceptor, _)| acceptor + 1 != donor && acceptor != donor));
+        }
+    }
+}
+
+#[test]
+fn test_backbone_dihedrals_and_ramachandran() {
+
+    let mut torsions = vec![(-57.0, -47.0); 8];
+    torsions.extend(vec![(-139.0, 135.0); 4]);
+    torsions.push((60.0, -120.0));
+    let pdb = parse_pdb_str(&backbone_pdb(&torsions)).unwrap();
+    let dihedrals = backbone_dihedrals(&pdb.models[0].view());
+    assert_eq!(dihedrals.len(), 13);
+
+    // Chain ends lack phi (N-terminus) or psi (C-terminus).
+    assert!(dihedrals[0].phi.is_none() && dihedrals[0].omega.is_none());
+    assert!(dihedrals[12].psi.is_none());
+    for (d, &(phi, psi)) in dihedrals.iter().zip(&torsions).skip(1).take(11) {
+        assert!((d.phi.unwrap() - phi).abs() < 0.1);
+        assert!((d.psi.unwrap() - psi).abs() < 0.1);
+        assert!((d.omega.unwrap().abs() - 180.0).abs() < 0.1);
+    }
+
+    // Helix and strand residues are favoured; the positive-phi alanine is an outlier.
+    assert_eq!(dihedrals[5].ramachandran(), Some(RamachandranRegion::Favoured));
+    assert_eq!(dihedrals[10].ramachandran(), Some(RamachandranRegion::Favoured));
+    let summary = ramachandran_summary(&dihedrals);
+    assert_eq!(summary.classified, 11);
+    assert_eq!(summary.outlier, 0.0);
+
+    assert_eq!(RamachandranRegion::classify(60.0, -120.0, ResidueClass::General), RamachandranRegion::Outlier);
+    assert_eq!(RamachandranRegion::classify(60.0, 40.0, ResidueClass::General), RamachandranRegion::Allowed);
+    assert_eq!(RamachandranRegion::classify(80.0, 170.0, ResidueClass::Glycine), RamachandranRegion::Favoured);
+    assert_eq!(RamachandranRegion::classify(-100.0, 150.0, ResidueClass::Proline), RamachandranRegion::Allowed);
+    assert_eq!(RamachandranRegion::classify(-65.0, 140.0, ResidueClass::Proline), RamachandranRegion::Favoured);
+}
+
+
+/// PDB text of one residue whose side chain atoms (after CB) are placed from the given chi angles.
+fn side_chain_pdb(name: &str, residue_seq: i32, side_chain: &[&str], chis: &[f32]) -> String {
+    let offset = residue_seq as f32 * 10.0;
+    let n = [offset, 0.0, 0.0];
+    let ca = [offset + 1.458, 0.0, 0.0];
+    let c = place_atom([offset, 1.0, 0.0], n, ca, 1.525, 111.2, -60.0);
+    let cb = place_atom(c, n, ca, 1.53, 110.5, -122.5);
+    let mut atoms = vec![("N", n), ("CA", ca), ("C", c), ("CB", cb)];
+    // N, CA, CB, then each side chain atom in turn
+    let mut branch = vec![n, ca, cb];
+    for (&atom, &chi) in side_chain.iter().zip(chis) {
+        let k = branch.len();
+        let p = place_atom(branch[k - 3], branch[k - 2], branch[k - 1], 1.52, 112.0, chi);
+        branch.push(p);
+        atoms.push((atom, p));
+    }
+    atoms
+        .iter()
+        .map(|&(atom, p)| {
+            format!(
+                "ATOM      1  {:<3} {} A{:>4}    {:>8.3}{:>8.3}{:>8.3}  1.00 90.00           {}\n",
+                atom, name, residue_seq, p[0], p[1], p[2], &atom[..1]
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_chi_angles_and_rotamers() {
+    let pdb = [
+        side_chain_pdb("MET", 1, &["CG", "SD", "CE"], &[-67.0, 180.0, 75.0]),
+        // Eclipsed chi2
+        side_chain_pdb("MET", 2, &["CG", "SD", "CE"], &[-67.0, 0.0, 75.0]),
+        // chi2 of the phenyl ring is defined modulo 180
+        side_chain_pdb("PHE", 3, &["CG", "CD1"], &[-65.0, 95.0]),
+        side_chain_pdb("LYS", 4, &["CG", "CD", "CE", "NZ"], &[-65.0, 180.0, 180.0, 60.0]),
+        // Truncated side chain: chi1 only, which is not enough for a leucine rotamer
+        side_chain_pdb("LEU", 5, &["CG"], &[-65.0]),
+        side_chain_pdb("ALA", 6, &[], &[]),
+    ]
+    .concat();
+    let pdb = parse_pdb_str(&pdb).unwrap();
+    let side_chains = side_chain_dihedrals(&pdb.models[0].view());
+    assert_eq!(side_chains.len(), 5);
+
+    let met = &side_chains[0];
+    for (chi, expected) in met.chi.iter().zip(&[-67.0, 180.0, 75.0]) {
+        let difference = (chi.unwrap() - expected).rem_euclid(360.0);
+        assert!(difference.min(360.0 - difference) < 0.1);
+    }
+    assert!(met.chi[3].is_none());
+    assert_eq!(met.rotamer(), Some(RotamerFit::Rotamer("mtp".to_string())));
+    assert_eq!(side_chains[1].rotamer(), Some(RotamerFit::Outlier));
+    assert_eq!(side_chains[2].rotamer(), Some(RotamerFit::Rotamer("m-85".to_string())));
+    assert_eq!(side_chains[3].rotamer(), Some(RotamerFit::Rotamer("mttp".to_string())));
+    assert!(side_chains[4].chi[0].is_some() && side_chains[4].chi[1].is_none());
+    assert_eq!(side_chains[4].rotamer(), None);
+
+    let summary = rotamer_summary(&side_chains);
+    assert_eq!(summary.evaluated, 4);
+    assert!((summary.outlier - 0.25).abs() < 1e-6);
+}
+
+#[test]
+fn test_gyration_tensor_shape() {
+    assert!(gyration_tensor(&[]).is_none());
+
+    // Cube corners: isotropic
+    let corners: Vec<AtomCoordinate> = (0..8)
+        .map(|i| {
+            let side = |bit: i32| if i & bit == 0 { -1.0 } else { 1.0 };
+            AtomCoordinate { z: side(4), ..ca("A", i, side(1), side(2)) }
+        })
+        .collect();
+    let corners: Vec<&AtomCoordinate> = corners.iter().collect();
+    let shape = gyration_tensor(&corners).unwrap().shape();
+    for l in shape.eigenvalues {
+        assert!((l - 1.0).abs() < 1e-5);
+    }
+    assert!(shape.asphericity.abs() < 1e-5 && shape.acylindricity.abs() < 1e-5 && shape.anisotropy.abs() < 1e-5);
+
+    // Evenly spaced points on the x = y diagonal: a rod along (1, 1, 0)
+    let rod: Vec<AtomCoordinate> = (0..10).map(|i| ca("A", i, i as f32, i as f32)).collect();
+    let rod: Vec<&AtomCoordinate> = rod.iter().collect();
+    let tensor = gyration_tensor(&rod).unwrap();
+    let shape = tensor.shape();
+    assert!(shape.eigenvalues[0] < 1e-5 && shape.eigenvalues[1] < 1e-5);
+    assert!((shape.eigenvalues[2] - 16.5).abs() < 1e-3);
+    assert!((shape.anisotropy - 1.0).abs() < 1e-5);
+    assert!((tensor.axes[[0, 2]].abs() - 0.5f32.sqrt()).abs() < 1e-4 && tensor.axes[[2, 2]].abs() < 1e-4);
+
+    // The trace is the squared radius of gyration.
+    let helix = parse_pdb_str(&ideal_helix_pdb(30)).unwrap();
+    let ca_atoms = get_ca_atoms(&helix.models[0]);
+    let tensor = gyration_tensor(&ca_atoms).unwrap();
+    assert!((tensor.radius_of_gyration() - radius_of_gyration(&ca_atoms)).abs() < 1e-3);
+    assert!(tensor.shape().asphericity > 0.0);
+}
+
+/// Rotates atoms by 30 degrees about z then 50 degrees about x, and shifts them.
+fn rotated(atoms: &[AtomCoordinate]) -> Vec<AtomCoordinate> {
+    let (a, b) = (30f32.to_radians(), 50f32.to_radians());
+    atoms
+        .iter()
+        .map(|atom| {
+            let (x, y) = (atom.x * a.cos() - atom.y * a.sin(), atom.x * a.sin() + atom.y * a.cos());
+            let (y, z) = (y * b.cos() - atom.z * b.sin(), y * b.sin() + atom.z * b.cos());
+            AtomCoordinate { x: x + 5.0, y: y - 3.0, z: z + 1.0, ..atom.clone() }
+        })
+        .collect()
+}
+
+#[test]
+fn test_oriented_box_and_convex_hull() {
+    // 2 x 3 x 4 box corners plus interior points
+    let mut points: Vec<AtomCoordinate> = (0..8)
+        .map(|i| {
+            let corner = |bit: i32, length: f32| if i & bit == 0 { 0.0 } else { length };
+            AtomCoordinate { z: corner(4, 4.0), ..ca("A", i, corner(1, 2.0), corner(2, 3.0)) }
+        })
+        .collect();
+    points.extend((0..5).map(|i| AtomCoordinate { z: 0.5 + i as f32 * 0.6, ..ca("A", 10 + i, 1.0, 1.5) }));
+    let tilted = rotated(&points);
+
+    for atoms in [&points, &tilted] {
+        let atoms: Vec<&AtomCoordinate> = atoms.iter().collect();
+        let obb = oriented_bounding_box(&atoms).unwrap();
+        assert!((obb.volume() - 24.0).abs() < 1e-3);
+        let mut lengths = obb.lengths;
+        lengths.sort_by(f32::total_cmp);
+        assert!((lengths[0] - 2.0).abs() < 1e-3 && (lengths[2] - 4.0).abs() < 1e-3);
+
+        let hull = convex_hull(&atoms);
+        assert!((hull.volume - 24.0).abs() < 1e-3);
+        assert!((hull.area - 52.0).abs() < 1e-3);
+        // 6 rectangular sides of 2 triangles, interior points left out
+        assert_eq!(hull.faces.len(), 12);
+        assert!(hull.faces.iter().flatten().all(|&i| i < 8));
+    }
+    let tilted: Vec<&AtomCoordinate> = tilted.iter().collect();
+    assert!(bounding_box_volume(&tilted) > 30.0);
+
+    // Flat or tiny sets have no volume.
+    let flat: Vec<AtomCoordinate> = (0..6).map(|i| ca("A", i, i as f32, (i * i) as f32)).collect();
+    let flat: Vec<&AtomCoordinate> = flat.iter().collect();
+    assert_eq!(convex_hull(&flat).volume, 0.0);
+    assert_eq!(convex_hull(&flat[..3]).area, 0.0);
+    assert!(oriented_bounding_box(&[]).is_none());
+
+    // Heavy atoms of a full backbone give the same hull whatever the frame.
+    let mut torsions = vec![(-57.0, -47.0); 10];
+    torsions.extend(vec![(-139.0, 135.0); 10]);
+    let backbone = parse_pdb_str(&backbone_pdb(&torsions)).unwrap();
+    let heavy = get_heavy_atoms(&backbone.models[0]);
+    assert_eq!(heavy.len(), 80);
+    let moved: Vec<AtomCoordinate> = rotated(&heavy.iter().map(|&a| a.clone()).collect::<Vec<_>>());
+    let moved: Vec<&AtomCoordinate> = moved.iter().collect();
+    let (hull, moved_hull) = (convex_hull(&heavy), convex_hull(&moved));
+    assert!(hull.volume > 0.0);
+    assert!((hull.volume - moved_hull.volume).abs() < 1e-3 * hull.volume);
+    assert!((hull.area - moved_hull.area).abs() < 1e-3 * hull.area);
+    let (obb, moved_obb) = (oriented_bounding_box(&heavy).unwrap(), oriented_bounding_box(&moved).unwrap());
+    assert!((obb.volume() - moved_obb.volume()).abs() < 1e-3 * obb.volume());
+}
+
+#[test]
+fn test_molecular_volume_and_packing_density() {
+    let options = VolumeOptions::default();
+    assert_eq!(molecular_volume(&[], &options).packing_density(), 0.0);
+
+    // A lone atom excludes exactly its van der Waals sphere.
+    let carbon = ca("A", 1, 0.0, 0.0);
+    let radius = van_der_waals_radius(&carbon);
+    assert!((radius - 1.77).abs() < 1e-3);
+    let sphere = 4.0 / 3.0 * std::f32::consts::PI * radius.powi(3);
+    let fine = VolumeOptions { grid_spacing: 0.2, ..options };
+    let lone = molecular_volume(&[&carbon], &fine);
+    assert!((lone.van_der_waals - sphere).abs() < 0.05 * sphere);
+    assert!((lone.molecular - lone.van_der_waals).abs() < 0.05 * sphere);
+
+    // Closely packed lattice of 5 x 5 x 5 atoms
+    let lattice: Vec<AtomCoordinate> = (0..125)
+        .map(|i| AtomCoordinate { z: (i / 25) as f32 * 3.0, ..ca("A", i, (i % 5) as f32 * 3.0, (i / 5 % 5) as f32 * 3.0) })
+        .collect();
+    let lattice: Vec<&AtomCoordinate> = lattice.iter().collect();
+    let packed = molecular_volume(&lattice, &options);
+
+    // Closed shell of 1000 atoms around a 15 A radius cavity
+    let golden = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+    let shell: Vec<AtomCoordinate> = (0..1000)
+        .map(|i| {
+            let z = 1.0 - 2.0 * (i as f32 + 0.5) / 1000.0;
+            let r = (1.0 - z * z).sqrt();
+            let theta = golden * i as f32;
+            AtomCoordinate { z: 15.0 * z, ..ca("A", i, 15.0 * r * theta.cos(), 15.0 * r * theta.sin()) }
+        })
+        .collect();
+    let shell: Vec<&AtomCoordinate> = shell.iter().collect();
+    let hollow = molecular_volume(&shell, &VolumeOptions { grid_spacing: 1.0, ..options });
+
+    // The cavity counts as molecular volume.
+    let enclosed = 4.0 / 3.0 * std::f32::consts::PI * (15.0 + radius).powi(3);
+    assert!((hollow.molecular - enclosed).abs() < 0.05 * enclosed);
+    assert!(packed.packing_density() > 0.7);
+    assert!(hollow.packing_density() < 0.5);
+}
+
+#[test]
+fn test_configurable_contact_order() {
+    // Straight CA trace, 3.8 A between consecutive residues
+    let straight: String = (1..=10)
+        .map(|i| {
+            format!("ATOM  {:>5}  CA  ALA A{:>4}    {:>8.3}{:>8.3}{:>8.3}  1.00 90.00           C\n", i, i, i as f32 * 3.8, 0.0, 0.0)
+        })
+        .collect();
+    let straight = parse_pdb_str(&straight).unwrap();
+    let view = straight.models[0].view();
+
+    // At 8 A: 9 contacts at |i-j| = 1 and 8 at |i-j| = 2.
+    let mut options = ContactOrderOptions {
+        contacts: ContactDefinition { atoms: ContactAtoms::Ca, cutoff: 8.0, min_separation: 1 },
+        short_protein: ShortProteinPolicy::Compute,
+    };
+    let co = contact_order_with(&view, &options).unwrap();
+    assert_eq!((co.contacts, co.residues), (17, 10));
+    assert!((co.absolute - 25.0 / 17.0).abs() < 1e-6);
+    assert!((co.relative - 25.0 / 170.0).abs() < 1e-6);
+
+    options.contacts.min_separation = 2;
+    let co = contact_order_with(&view, &options).unwrap();
+    assert_eq!(co.contacts, 8);
+    assert!((co.absolute - 2.0).abs() < 1e-6 && (co.relative - 0.2).abs() < 1e-6);
+
+    // Short protein policies
+    options.short_protein = ShortProteinPolicy::SkipBelow(20);
+    assert!(contact_order_with(&view, &options).is_none());
+    options.short_protein = ShortProteinPolicy::ZeroBelow(20);
+    assert_eq!(contact_order_with(&view, &options).unwrap().relative, 0.0);
+
+    // The legacy preset matches `contact_order`.
+    let helix = parse_pdb_str(&ideal_helix_pdb(30)).unwrap();
+    let legacy = contact_order_with(&helix.models[0].view(), &ContactOrderOptions::LEGACY).unwrap();
+    assert!((legacy.relative * 100.0 - contact_order(&get_ca_atoms(&helix.models[0]))).abs() < 1e-4);
+
+    // Heavy atoms reach further than CA atoms; CB falls back to CA without side chains.
+    let backbone = parse_pdb_str(&backbone_pdb(&[(-57.0, -47.0); 12])).unwrap();
+    let view = backbone.models[0].view();
+    let count = |atoms| residue_contacts(&view, &ContactDefinition { atoms, cutoff: 5.0, min_separation: 2 }).contacts.len();
+    assert!(count(ContactAtoms::HeavyAtoms) > count(ContactAtoms::Ca));
+    assert_eq!(count(ContactAtoms::Cb), count(ContactAtoms::Ca));
+    let contacts = residue_contacts(&view, &ContactDefinition { atoms: ContactAtoms::Ca, cutoff: 5.0, min_separation: 2 });
+    assert_eq!(contacts.residues.len(), 12);
+    assert!(contacts.contacts.iter().all(|c| c.i < c.j && c.separation >= 2));
+}
+
+#[test]
+fn test_distance_matrix_layouts() {
+    // Straight CA trace, 3.8 A between consecutive residues
+    let straight: String = (1..=6)
+        .map(|i| {
+            format!("ATOM  {:>5}  CA  ALA A{:>4}    {:>8.3}{:>8.3}{:>8.3}  1.00 90.00           C\n", i, i, i as f32 * 3.8, 0.0, 0.0)
+        })
+        .collect();
+    let straight = parse_pdb_str(&straight).unwrap();
+    let view = straight.models[0].view();
+
+    let full = distance_matrix(&view, ContactAtoms::Ca, MatrixLayout::Full);
+    let condensed = distance_matrix(&view, ContactAtoms::Ca, MatrixLayout::Condensed);
+    assert_eq!(full.residues.len(), 6);
+    assert_eq!(full.distances.shape(), vec![6, 6]);
+    assert_eq!(condensed.distances.shape(), vec![15]);
+    for i in 0..6 {
+        for j in 0..6 {
+            let expected = (i as f32 - j as f32).abs() * 3.8;
+            assert!((full.distances.get(i, j) - expected).abs() < 1e-4);
+            assert_eq!(condensed.distances.get(i, j), full.distances.get(i, j));
+        }
+    }
+    // Condensed values follow the upper triangle row after row.
+    assert!((condensed.distances.values[4] - 19.0).abs() < 1e-4 && (condensed.distances.values[5] - 3.8).abs() < 1e-4);
+    assert_eq!(condensed.distances.with_layout(MatrixLayout::Full), full.distances);
+    assert_eq!(full.distances.to_array(), condensed.distances.to_array());
+
+    let contacts = full.distances.contact_map(8.0);
+    assert_eq!(contacts.values.iter().map(|&c| c as usize).sum::<usize>(), 6 + 2 * (5 + 4));
+    assert_eq!(contacts.get(0, 3), 0);
+    let half = condensed.distances.to_f16();
+    assert!((half.get(0, 5).to_f32() - 19.0).abs() < 0.02);
+}
+
+#[test]
+fn test_neighbour_search_matches_brute_force() {
+    let helix = parse_pdb_str(&backbone_pdb(&[(-57.0, -47.0); 30])).unwrap();
+    let atoms = get_heavy_atoms(&helix.models[0]);
+    let search = NeighbourSearch::from_atoms(&atoms);
+    assert_eq!(search.len(), atoms.len());
+
+    let distance2 = |a: &AtomCoordinate, b: &AtomCoordinate| (a.x - b.x).powi(2) + (a.y - b.y).powi(2) + (a.z - b.z).powi(2);
+    let brute: Vec<(usize, usize)> = (0..atoms.len())
+        .flat_map(|i| ((i + 1)..atoms.len()).map(move |j| (i, j)))
+        .filter(|&(i, j)| distance2(atoms[i], atoms[j]) <= 16.0)
+        .collect();
+    assert_eq!(search.pairs_within(4.0), brute);
+
+    let centre = [atoms[10].x, atoms[10].y, atoms[10].z];
+    let mut around: Vec<usize> = search.within(centre, 4.0).map(|(i, _)| i).collect();
+    around.sort_unstable();
+    let expected: Vec<usize> = (0..atoms.len()).filter(|&i| distance2(atoms[i], atoms[10]) <= 16.0).collect();
+    assert_eq!(around, expected);
+
+    // The contact map from the neighbour search agrees with the thresholded distance matrix.
+    let view = helix.models[0].view();
+    for layout in [MatrixLayout::Full, MatrixLayout::Condensed] {
+        for atoms in [ContactAtoms::Ca, ContactAtoms::HeavyAtoms] {
+            let expected = distance_matrix(&view, atoms, layout).distances.contact_map(6.0);
+            assert_eq!(contact_map(&view, atoms, 6.0, layout).contacts, expected);
+        }
+    }
+}
+
+#[test]
+fn test_confidence_segmentation() {
+    // Disordered N-terminus, confident core, a disordered loop, confident stretch, short dip, disordered C-terminus
+    let plddt: Vec<f32> = [(4, 30.0), (10, 90.0), (6, 35.0), (8, 85.0), (2, 40.0), (5, 80.0), (3, 20.0)]
+        .iter()
+        .flat_map(|&(n, value)| std::iter::repeat_n(value, n))
+        .collect();
+    let raw = SegmentationOptions { window: 1, min_segment: 3, ..SegmentationOptions::default() };
+    let profile = confidence_profile(&plddt, &raw);
+    assert_eq!(profile.smoothed, plddt);
+    assert_eq!(profile.low_segments, vec![0..4, 14..20, 35..38]);
+    assert_eq!(profile.low_residues(), 13);
+    assert_eq!((profile.disordered_n_terminus, profile.disordered_c_terminus), (4, 3));
+    assert_eq!(profile.longest_high_confidence, 4..14);
+
+    // Smoothing over 3 residues averages the edges, truncating the window at the ends.
+    let smoothed = confidence_profile(&plddt, &SegmentationOptions { window: 3, ..raw });
+    assert_eq!(smoothed.smoothed[0], 30.0);
+    assert!((smoothed.smoothed[4] - (30.0 + 90.0 + 90.0) / 3.0).abs() < 1e-4);
+    assert!((smoothed.smoothed[37] - 20.0).abs() < 1e-4);
+    assert_eq!(smoothed.low_segments.first(), Some(&(0..3)));
+    // An even window is widened to the next odd width.
+    assert_eq!(confidence_profile(&plddt, &SegmentationOptions { window: 2, ..raw }), smoothed);
+
+    // A chain low throughout is N-terminal disorder only.
+    let all_low = confidence_profile(&[20.0; 6], &raw);
+    assert_eq!((all_low.disordered_n_terminus, all_low.disordered_c_terminus), (6, 0));
+    assert!(all_low.longest_high_confidence.is_empty());
+    assert_eq!(confidence_profile(&[], &raw), Default::default());
+
+    // Chains are segmented on their own; the helix fixture has B-factors from 42 to 100.
+    let helix = parse_pdb_str(&ideal_helix_pdb(30)).unwrap();
+    let chains = chain_confidence(&helix.models[0].view(), &raw);
+    assert_eq!(chains.len(), 1);
+    assert_eq!(chains[0].residues.len(), 30);
+    assert_eq!(chains[0].profile.disordered_n_terminus, 4);
+    assert_eq!(chains[0].profile.longest_high_confidence, 14..30);
+}
+
+#[test]
+fn test_confidence_statistics_thresholds() {
+    // pLDDT runs from 42 to 100 in steps of 2.
+    let pdb = parse_pdb_str(&ideal_helix_pdb(30)).unwrap();
+    let ca_atoms = get_ca_atoms(&pdb.models[0]);
+
+    let statistics = confidence_statistics(&ca_atoms, &ConfidenceThresholds::default());
+    assert_eq!(statistics.residues, 30);
+    assert!((statistics.mean - 71.0).abs() < 1e-3);
+    let thresholds: Vec<f32> = statistics.fractions.iter().map(|f| f.threshold).collect();
+    assert_eq!(thresholds, vec![50.0, 70.0, 90.0]);
+    let fractions: Vec<f32> = statistics.fractions.iter().map(|f| f.fraction).collect();
+    assert_eq!(fractions, vec![25.0 / 30.0, 15.0 / 30.0, 5.0 / 30.0]);
+
+    // The legacy tuple holds the same values as percentages.
+    let (mean, above_50, above_70, above_90) = plddt_statistics(&ca_atoms);
+    assert_eq!(mean, statistics.mean);
+    assert_eq!([above_50, above_70, above_90], [0, 1, 2].map(|i| statistics.fractions[i].percent()));
+    assert!((above_70 - 50.0).abs() < 1e-4);
+
+    // Experimental B-factors count the residues under each threshold.
+    let thresholds = ConfidenceThresholds { source: ConfidenceSource::BFactor, values: vec![50.0, 101.0] };
+    let statistics = confidence_statistics(&ca_atoms, &thresholds);
+    assert_eq!(statistics.fractions[0].fraction, 4.0 / 30.0);
+    assert_eq!(statistics.fractions[1].fraction, 1.0);
+    assert_eq!(ConfidenceThresholds::of(ConfidenceSource::BFactor).values, vec![30.0, 50.0, 80.0]);
+
+    let empty = confidence_statistics(&[], &ConfidenceThresholds::default());
+    assert_eq!((empty.mean, empty.fractions[0].fraction), (0.0, 0.0));
+}
+
+#[test]
+fn test_pae_summary_by_domain() {
+    // Two domains of 2 residues: 2 A within, 20 A across, 0 on the diagonal.
+    let values: Vec<f32> = (0..16)
+        .map(|k| {
+            let (i, j) = (k / 4, k % 4);
+            if i == j { 0.0 } else if i / 2 == j / 2 { 2.0 } else { 20.0 }
+        })
+        .collect();
+    let pae = PredictedAlignedError { size: 4, values, max: Some(31.75) };
+
+    let summary = pae_summary(&pae, &[0..2, 2..4]);
+    assert!((summary.mean - (4.0 * 2.0 + 8.0 * 20.0) / 12.0).abs() < 1e-5);
+    assert!((summary.fraction_confident - 4.0 / 12.0).abs() < 1e-6);
+    assert_eq!((summary.intra_domain, summary.inter_domain), (Some(2.0), Some(20.0)));
+
+    // A single domain has no inter-domain pairs; ranges past the matrix are ignored.
+    let single = pae_summary(&pae, std::slice::from_ref(&(0..4)));
+    assert_eq!(single.inter_domain, None);
+    assert_eq!(single.mean, summary.mean);
+    let mismatched = pae_summary(&pae, &[0..2, 2..6]);
+    assert_eq!((mismatched.intra_domain, mismatched.inter_domain), (Some(2.0), None));
+
+    let empty = pae_summary(&PredictedAlignedError { size: 0, values: Vec::new(), max: None }, &[]);
+    assert_eq!((empty.mean, empty.intra_domain), (0.0, None));
+}
+
+/// Two 4 x 4 x 4 lattices of CA atoms, 3.8 A apart, joined by a straight linker of 5 residues with a pLDDT of 30.
+fn two_domain_pdb() -> String {
+    let cube = |offset: f32| {
+        (0..64).map(move |k| {
+            let (x, y, z) = (k % 4, (k / 4) % 4, k / 16);
+            // Snake through the lattice so consecutive residues stay 3.8 A apart.
+            let x = if y % 2 == 1 { 3 - x } else { x };
+            let y = if z % 2 == 1 { 3 - y } else { y };
+            ([offset + x as f32 * 3.8, y as f32 * 3.8, z as f32 * 3.8], 90.0)
+        })
+    };
+    let linker = (1..=5).map(|k| ([11.4 + k as f32 * 3.8, 0.0, 0.0], 30.0));
+    cube(0.0)
+        .chain(linker)
+        .chain(cube(34.2))
+        .enumerate()
+        .map(|(i, ([x, y, z], b))| {
+            format!("ATOM  {:>5}  CA  ALA A{:>4}    {:>8.3}{:>8.3}{:>8.3}  1.00{:>6.2}           C\n", i + 1, i + 1, x, y, z, b)
+        })
+        .collect()
+}
+
+#[test]
+fn test_domain_parsing() {
+    let pdb = parse_pdb_str(&two_domain_pdb()).unwrap();
+    let view = pdb.models[0].view();
+
+    // The linker is the cheapest cut; each lattice is too compact to be split further.
+    let domains = parse_domains(&view, &DomainOptions::default(), None);
+    assert_eq!(domains.method, DomainMethod::Contacts);
+    assert_eq!(domains.residues.len(), 133);
+    assert_eq!(domains.domains.len(), 2);
+    assert_eq!(domains.unassigned(), 0);
+    let first: Vec<usize> = domains.domains[0].indices().collect();
+    let second: Vec<usize> = domains.domains[1].indices().collect();
+    assert!((0..64).all(|i| first.contains(&i)) && (69..133).all(|i| second.contains(&i)));
+
+    // Low-confidence residues are left out as a linker.
+    let options = DomainOptions { min_plddt: Some(50.0), ..Default::default() };
+    let domains = parse_domains(&view, &options, None);
+    assert_eq!(domains.domains.iter().map(|d| d.ranges.clone()).collect::<Vec<_>>(), vec![vec![0..64], vec![69..133]]);
+    assert_eq!(domains.unassigned(), 5);
+
+    // PAE clustering follows the matrix, not the geometry.
+    let values: Vec<f32> = (0..133 * 133).map(|k| if (k / 133 < 100) == (k % 133 < 100) { 1.0 } else { 20.0 }).collect();
+    let pae = PredictedAlignedError { size: 133, values, max: None };
+    let options = DomainOptions { method: DomainMethod::Pae, ..Default::default() };
+    let domains = parse_domains(&view, &options, Some(&pae));
+    assert_eq!(domains.method, DomainMethod::Pae);
+    assert_eq!(domains.domains.iter().map(|d| d.ranges.clone()).collect::<Vec<_>>(), vec![vec![0..100], vec![100..133]]);
+
+    // A matrix of another size falls back to contacts.
+    let small = pae.submatrix(&[0, 1, 2]);
+    assert_eq!((small.size, small.get(0, 2)), (3, 1.0));
+    assert_eq!(parse_domains(&view, &options, Some(&small)).method, DomainMethod::Contacts);
+}
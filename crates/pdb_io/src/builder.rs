@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::error::PdbParseError;
+use crate::{AltLocPolicy, AtomCoordinate, Chain, Model, ParsedPDB, SeqresChain};
+
+/// Fields of one ATOM / HETATM record, borrowed from the line being read.
+pub(crate) struct AtomRecord<'l> {
+    pub(crate) atom_name: &'l str,
+    pub(crate) alt_loc: Option<char>,
+    pub(crate) residue_name: &'l str,
+    pub(crate) chain_id: &'l str,
+    pub(crate) residue_seq: i32,
+    pub(crate) insertion_code: Option<char>,
+    pub(crate) occupancy: f32,
+    pub(crate) bfactor: f32,
+    pub(crate) element: &'l str,
+    pub(crate) is_hetatm: bool,
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) z: f32,
+}
+
+impl AtomRecord<'_> {
+    fn to_atom(&self) -> AtomCoordinate {
+        AtomCoordinate {
+            atom_name: self.atom_name.to_string(),
+            alt_loc: self.alt_loc,
+            residue_name: self.residue_name.to_string(),
+            chain_id: self.chain_id.to_string(),
+            residue_seq: self.residue_seq,
+            insertion_code: self.insertion_code,
+            occupancy: self.occupancy,
+            bfactor: self.bfactor,
+            element: self.element.to_string(),
+            is_hetatm: self.is_hetatm,
+            x: self.x,
+            y: self.y,
+            z: self.z,
+        }
+    }
+}
+
+/// Receives records from the PDB and mmCIF readers and builds their output representation.
+pub(crate) trait AtomSink {
+    type Output;
+
+    fn start_model(&mut self, serial: i32);
+    fn end_model(&mut self);
+    fn terminate_chain(&mut self);
+    fn push_atom(&mut self, atom: AtomRecord<'_>);
+    fn finish(self, altloc: AltLocPolicy, seqres: Vec<SeqresChain>, warnings: Vec<PdbParseError>) -> Self::Output;
+}
+
+/// Chain an atom is dispatched to, within the current model.
+pub(crate) enum Placement {
+    Existing(usize),
+    New,
+}
+
+/// Model and chain bookkeeping shared by the builders.
+///
+/// Without MODEL records everything lands in a single model with serial 1.
+/// A TER record closes the current chain: a following ATOM record opens a new
+/// chain even if the identifier is reused, while HETATM records (ligands, waters)
+/// join the last chain carrying their identifier.
+pub(crate) struct ChainTracker {
+    in_model: bool,
+    chain_open: bool,
+    last_serial: Option<i32>,
+    chain_ids: Vec<String>,
+}
+
+impl ChainTracker {
+    pub(crate) fn new() -> Self {
+        ChainTracker { in_model: false, chain_open: false, last_serial: None, chain_ids: Vec::new() }
+    }
+
+    pub(crate) fn start_model(&mut self, serial: i32) {
+        self.in_model = true;
+        self.chain_open = false;
+        self.last_serial = Some(serial);
+        self.chain_ids.clear();
+    }
+
+    pub(crate) fn end_model(&mut self) {
+        self.in_model = false;
+        self.chain_open = false;
+    }
+
+    pub(crate) fn terminate_chain(&mut self) {
+        self.chain_open = false;
+    }
+
+    /// Serial of the model to open before placing an atom found outside of MODEL / ENDMDL.
+    pub(crate) fn implicit_model(&mut self) -> Option<i32> {
+        if self.in_model {
+            return None;
+        }
+        let serial = self.last_serial.map_or(1, |s| s + 1);
+        self.start_model(serial);
+        Some(serial)
+    }
+
+    pub(crate) fn place(&mut self, chain_id: &str, is_hetatm: bool) -> Placement {
+        if self.chain_open && self.chain_ids.last().is_some_and(|id| id == chain_id) {
+            return Placement::Existing(self.chain_ids.len() - 1);
+        }
+        if is_hetatm {
+            if let Some(index) = self.chain_ids.iter().rposition(|id| id == chain_id) {
+                return Placement::Existing(index);
+            }
+        }
+        self.chain_ids.push(chain_id.to_string());
+        self.chain_open = true;
+        Placement::New
+    }
+}
+
+/// Builds the [`ParsedPDB`] representation, one `AtomCoordinate` per atom.
+pub(crate) struct StructureBuilder {
+    models: Vec<Model>,
+    tracker: ChainTracker,
+}
+
+impl StructureBuilder {
+    pub(crate) fn new() -> Self {
+        StructureBuilder { models: Vec::new(), tracker: ChainTracker::new() }
+    }
+}
+
+impl AtomSink for StructureBuilder {
+    type Output = ParsedPDB;
+
+    fn start_model(&mut self, serial: i32) {
+        self.models.push(Model { serial, chains: Vec::new() });
+        self.tracker.start_model(serial);
+    }
+
+    fn end_model(&mut self) {
+        self.tracker.end_model();
+    }
+
+    fn terminate_chain(&mut self) {
+        self.tracker.terminate_chain();
+    }
+
+    fn push_atom(&mut self, atom: AtomRecord<'_>) {
+        if let Some(serial) = self.tracker.implicit_model() {
+            self.models.push(Model { serial, chains: Vec::new() });
+        }
+        let model = self.models.last_mut().expect("a model is open");
+
+        match self.tracker.place(atom.chain_id, atom.is_hetatm) {
+            Placement::Existing(index) => model.chains[index].atoms.push(atom.to_atom()),
+            Placement::New => model.chains.push(Chain { id: atom.chain_id.to_string(), atoms: vec![atom.to_atom()] }),
+        }
+    }
+
+    fn finish(mut self, altloc: AltLocPolicy, seqres: Vec<SeqresChain>, warnings: Vec<PdbParseError>) -> ParsedPDB {
+        for model in &mut self.models {
+            for chain in &mut model.chains {
+                let atoms = std::mem::take(&mut chain.atoms);
+                chain.atoms = select_altlocs(atoms, altloc);
+            }
+        }
+        ParsedPDB { models: self.models, seqres, warnings }
+    }
+}
+
+/// Applies the alternate location policy, keeping atoms in file order.
+fn select_altlocs(atoms: Vec<AtomCoordinate>, policy: AltLocPolicy) -> Vec<AtomCoordinate> {
+    if policy == AltLocPolicy::KeepAll {
+        return atoms;
+    }
+
+    // (residue number, insertion code, atom name); atoms already belong to a single chain.
+    let keys = atoms.iter().map(|atom| {
+        let key = atom.alt_loc.map(|_| (atom.residue_seq, atom.insertion_code, atom.atom_name.as_str()));
+        (key, atom.occupancy)
+    });
+    let selected = select_altloc_indices(keys, policy);
+
+    let mut atoms: Vec<Option<AtomCoordinate>> = atoms.into_iter().map(Some).collect();
+    selected.into_iter().map(|i| atoms[i].take().expect("indices are unique")).collect()
+}
+
+/// Indices of the atoms to keep, in output order.
+///
+/// Each item is the atom's alternate location key (`None` when the atom has no
+/// alternate location) and its occupancy. With [`AltLocPolicy::HighestOccupancy`]
+/// a better conformer takes the place of the first one seen.
+pub(crate) fn select_altloc_indices<K: Hash + Eq>(
+    keys: impl Iterator<Item = (Option<K>, f32)>,
+    policy: AltLocPolicy,
+) -> Vec<usize> {
+    let mut selected: Vec<(usize, f32)> = Vec::new();
+    // Alternate location key -> position in `selected`
+    let mut seen: HashMap<K, usize> = HashMap::new();
+
+    for (index, (key, occupancy)) in keys.enumerate() {
+        let Some(key) = key else {
+            selected.push((index, occupancy));
+            continue;
+        };
+
+        match seen.get(&key) {
+            None => {
+                seen.insert(key, selected.len());
+                selected.push((index, occupancy));
+            }
+            Some(&position) => {
+                if policy == AltLocPolicy::HighestOccupancy && occupancy > selected[position].1 {
+                    selected[position] = (index, occupancy);
+                }
+            }
+        }
+    }
+
+    selected.into_iter().map(|(index, _)| index).collect()
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::ops::Range;
+use std::path::Path;
+
+use half::f16;
+use snafu::prelude::*;
+
+use crate::builder::{select_altloc_indices, AtomRecord, AtomSink, ChainTracker, Placement};
+use crate::compression::decompress_reader;
+use crate::error::{OpenSnafu, PdbParseError, ReadSnafu};
+use crate::mmcif::read_mmcif;
+use crate::pdb::read_pdb;
+use crate::{AltLocPolicy, AtomCoordinate, Chain, Model, ParseOptions, ParsedPDB, SeqresChain, StructureFormat};
+
+/// Index of a string in a [`NameTable`].
+pub type NameId = u32;
+
+/// Interned atom names, residue names, elements and chain identifiers of one structure.
+///
+/// A structure only uses a few dozen distinct names, so each atom stores
+/// small indices instead of owned strings.
+#[derive(Debug, Clone, Default)]
+pub struct NameTable {
+    names: Vec<Box<str>>,
+    ids: HashMap<Box<str>, NameId>,
+}
+
+impl NameTable {
+    /// Returns the identifier of `name`, allocating only the first time it is seen.
+    pub fn intern(&mut self, name: &str) -> NameId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len() as NameId;
+        self.names.push(name.into());
+        self.ids.insert(name.into(), id);
+        id
+    }
+
+    pub fn get(&self, id: NameId) -> &str {
+        &self.names[id as usize]
+    }
+
+    /// Identifier of an already interned name.
+    pub fn find(&self, name: &str) -> Option<NameId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Storage precision of the B-factor column in a [`CompactPDB`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BFactorPrecision {
+    #[default]
+    F32,
+    /// Half precision, about 3 significant digits: enough for pLDDT
+    F16,
+}
+
+#[derive(Debug, Clone)]
+pub enum BFactors {
+    F32(Vec<f32>),
+    F16(Vec<f16>),
+}
+
+impl BFactors {
+    fn with_precision(precision: BFactorPrecision) -> Self {
+        match precision {
+            BFactorPrecision::F32 => BFactors::F32(Vec::new()),
+            BFactorPrecision::F16 => BFactors::F16(Vec::new()),
+        }
+    }
+
+    pub fn get(&self, index: usize) -> f32 {
+        match self {
+            BFactors::F32(values) => values[index],
+            BFactors::F16(values) => values[index].to_f32(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            BFactors::F32(values) => values.len(),
+            BFactors::F16(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn push(&mut self, value: f32) {
+        match self {
+            BFactors::F32(values) => values.push(value),
+            BFactors::F16(values) => values.push(f16::from_f32(value)),
+        }
+    }
+
+    fn gather(&self, order: &[usize]) -> Self {
+        match self {
+            BFactors::F32(values) => BFactors::F32(order.iter().map(|&i| values[i]).collect()),
+            BFactors::F16(values) => BFactors::F16(order.iter().map(|&i| values[i]).collect()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompactChain {
+    pub id: NameId,
+    /// Atom indices of the chain
+    pub atoms: Range<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompactModel {
+    /// Serial number from the MODEL record, 1 when the file has none
+    pub serial: i32,
+    /// Indices into [`CompactPDB::chains`]
+    pub chains: Range<usize>,
+}
+
+/// Struct-of-arrays counterpart of [`ParsedPDB`] for large-scale runs.
+///
+/// Atom `i` is described by the `i`-th entry of every per-atom column. Atoms
+/// are stored model after model and chain after chain, in the same order as
+/// [`ParsedPDB::atoms`].
+#[derive(Debug)]
+pub struct CompactPDB {
+    pub names: NameTable,
+    pub models: Vec<CompactModel>,
+    pub chains: Vec<CompactChain>,
+
+    pub atom_names: Vec<NameId>,
+    pub alt_locs: Vec<Option<char>>,
+    pub residue_names: Vec<NameId>,
+    pub residue_seqs: Vec<i32>,
+    pub insertion_codes: Vec<Option<char>>,
+    pub elements: Vec<NameId>,
+    pub is_hetatm: Vec<bool>,
+    pub coords: Vec<[f32; 3]>,
+    pub occupancies: Vec<f32>,
+    pub bfactors: BFactors,
+
+    /// Deposited sequences, as in [`ParsedPDB::seqres`]
+    pub seqres: Vec<SeqresChain>,
+    /// Records skipped in lenient mode, always empty otherwise
+    pub warnings: Vec<PdbParseError>,
+}
+
+impl CompactPDB {
+    pub fn len(&self) -> usize {
+        self.coords.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coords.is_empty()
+    }
+
+    pub fn atom_name(&self, index: usize) -> &str {
+        self.names.get(self.atom_names[index])
+    }
+
+    pub fn residue_name(&self, index: usize) -> &str {
+        self.names.get(self.residue_names[index])
+    }
+
+    pub fn element(&self, index: usize) -> &str {
+        self.names.get(self.elements[index])
+    }
+
+    /// Atom index range of a model.
+    pub fn model_atoms(&self, model: &CompactModel) -> Range<usize> {
+        let chains = &self.chains[model.chains.clone()];
+        match (chains.first(), chains.last()) {
+            (Some(first), Some(last)) => first.atoms.start..last.atoms.end,
+            _ => 0..0,
+        }
+    }
+
+    /// Materialises one atom as an [`AtomCoordinate`].
+    pub fn atom(&self, index: usize, chain: &CompactChain) -> AtomCoordinate {
+        let [x, y, z] = self.coords[index];
+        AtomCoordinate {
+            atom_name: self.atom_name(index).to_string(),
+            alt_loc: self.alt_locs[index],
+            residue_name: self.residue_name(index).to_string(),
+            chain_id: self.names.get(chain.id).to_string(),
+            residue_seq: self.residue_seqs[index],
+            insertion_code: self.insertion_codes[index],
+            occupancy: self.occupancies[index],
+            bfactor: self.bfactors.get(index),
+            element: self.element(index).to_string(),
+            is_hetatm: self.is_hetatm[index],
+            x,
+            y,
+            z,
+        }
+    }
+
+    /// Converts to the owned representation, e.g. to feed functions taking [`ParsedPDB`].
+    pub fn to_parsed(&self) -> ParsedPDB {
+        let models = self
+            .models
+            .iter()
+            .map(|model| Model {
+                serial: model.serial,
+                chains: self.chains[model.chains.clone()]
+                    .iter()
+                    .map(|chain| Chain {
+                        id: self.names.get(chain.id).to_string(),
+                        atoms: chain.atoms.clone().map(|i| self.atom(i, chain)).collect(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        ParsedPDB { models, seqres: self.seqres.clone(), warnings: Vec::new() }
+    }
+}
+
+/// Fills the per-atom columns in file order, then groups them by chain at the end.
+///
+/// HETATM records may join a chain that is not the last one, so every atom
+/// remembers its chain slot and the columns are reordered once in `finish`.
+pub(crate) struct CompactBuilder {
+    tracker: ChainTracker,
+    names: NameTable,
+    models: Vec<CompactModel>,
+    /// Chain identifier of every chain slot, across models
+    chain_ids: Vec<NameId>,
+    /// Chain slot of every atom
+    atom_chains: Vec<u32>,
+
+    atom_names: Vec<NameId>,
+    alt_locs: Vec<Option<char>>,
+    residue_names: Vec<NameId>,
+    residue_seqs: Vec<i32>,
+    insertion_codes: Vec<Option<char>>,
+    elements: Vec<NameId>,
+    is_hetatm: Vec<bool>,
+    coords: Vec<[f32; 3]>,
+    occupancies: Vec<f32>,
+    bfactors: BFactors,
+}
+
+impl CompactBuilder {
+    pub(crate) fn new(precision: BFactorPrecision) -> Self {
+        CompactBuilder {
+            tracker: ChainTracker::new(),
+            names: NameTable::default(),
+            models: Vec::new(),
+            chain_ids: Vec::new(),
+            atom_chains: Vec::new(),
+            atom_names: Vec::new(),
+            alt_locs: Vec::new(),
+            residue_names: Vec::new(),
+            residue_seqs: Vec::new(),
+            insertion_codes: Vec::new(),
+            elements: Vec::new(),
+            is_hetatm: Vec::new(),
+            coords: Vec::new(),
+            occupancies: Vec::new(),
+            bfactors: BFactors::with_precision(precision),
+        }
+    }
+
+    fn open_model(&mut self, serial: i32) {
+        let start = self.chain_ids.len();
+        self.models.push(CompactModel { serial, chains: start..start });
+    }
+}
+
+impl AtomSink for CompactBuilder {
+    type Output = CompactPDB;
+
+    fn start_model(&mut self, serial: i32) {
+        self.open_model(serial);
+        self.tracker.start_model(serial);
+    }
+
+    fn end_model(&mut self) {
+        self.tracker.end_model();
+    }
+
+    fn terminate_chain(&mut self) {
+        self.tracker.terminate_chain();
+    }
+
+    fn push_atom(&mut self, atom: AtomRecord<'_>) {
+        if let Some(serial) = self.tracker.implicit_model() {
+            self.open_model(serial);
+        }
+        let model = self.models.last_mut().expect("a model is open");
+
+        let slot = match self.tracker.place(atom.chain_id, atom.is_hetatm) {
+            Placement::Existing(index) => model.chains.start + index,
+            Placement::New => {
+                self.chain_ids.push(self.names.intern(atom.chain_id));
+                model.chains.end += 1;
+                model.chains.end - 1
+            }
+        };
+
+        self.atom_chains.push(slot as u32);
+        self.atom_names.push(self.names.intern(atom.atom_name));
+        self.alt_locs.push(atom.alt_loc);
+        self.residue_names.push(self.names.intern(atom.residue_name));
+        self.residue_seqs.push(atom.residue_seq);
+        self.insertion_codes.push(atom.insertion_code);
+        self.elements.push(self.names.intern(atom.element));
+        self.is_hetatm.push(atom.is_hetatm);
+        self.coords.push([atom.x, atom.y, atom.z]);
+        self.occupancies.push(atom.occupancy);
+        self.bfactors.push(atom.bfactor);
+    }
+
+    fn finish(self, altloc: AltLocPolicy, seqres: Vec<SeqresChain>, warnings: Vec<PdbParseError>) -> CompactPDB {
+        // Stable sort by chain slot: chains of a model are contiguous, file order is kept inside a chain.
+        let mut order: Vec<usize> = (0..self.atom_chains.len()).collect();
+        order.sort_by_key(|&i| self.atom_chains[i]);
+
+        if altloc != AltLocPolicy::KeepAll {
+            let keys = order.iter().map(|&i| {
+                let key = self.alt_locs[i].map(|_| {
+                    (self.atom_chains[i], self.residue_seqs[i], self.insertion_codes[i], self.atom_names[i])
+                });
+                (key, self.occupancies[i])
+            });
+            order = select_altloc_indices(keys, altloc).into_iter().map(|k| order[k]).collect();
+        }
+
+        let mut chains: Vec<CompactChain> = self.chain_ids.iter().map(|&id| CompactChain { id, atoms: 0..0 }).collect();
+        for (position, &i) in order.iter().enumerate() {
+            let chain = &mut chains[self.atom_chains[i] as usize];
+            if chain.atoms.is_empty() {
+                chain.atoms = position..position;
+            }
+            chain.atoms.end = position + 1;
+        }
+
+        fn gather<T: Copy>(values: &[T], order: &[usize]) -> Vec<T> {
+            order.iter().map(|&i| values[i]).collect()
+        }
+
+        CompactPDB {
+            models: self.models,
+            chains,
+            atom_names: gather(&self.atom_names, &order),
+            alt_locs: gather(&self.alt_locs, &order),
+            residue_names: gather(&self.residue_names, &order),
+            residue_seqs: gather(&self.residue_seqs, &order),
+            insertion_codes: gather(&self.insertion_codes, &order),
+            elements: gather(&self.elements, &order),
+            is_hetatm: gather(&self.is_hetatm, &order),
+            coords: gather(&self.coords, &order),
+            occupancies: gather(&self.occupancies, &order),
+            bfactors: self.bfactors.gather(&order),
+            names: self.names,
+            seqres,
+            warnings,
+        }
+    }
+}
+
+/// Parses a PDB or mmCIF file straight into the compact representation,
+/// picking the reader from the file extension.
+pub fn parse_structure_compact<P: AsRef<Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<CompactPDB, PdbParseError> {
+    let path = path.as_ref();
+    let file = File::open(path).context(OpenSnafu { path })?;
+    parse_structure_compact_from_reader(BufReader::new(file), StructureFormat::from_path(path), options)
+}
+
+/// Compact counterpart of [`parse_structure_from_reader`](crate::parse_structure_from_reader).
+pub fn parse_structure_compact_from_reader<R: BufRead>(
+    reader: R,
+    format: StructureFormat,
+    options: &ParseOptions,
+) -> Result<CompactPDB, PdbParseError> {
+    let reader = decompress_reader(reader).context(ReadSnafu { line: 1usize })?;
+    let builder = CompactBuilder::new(options.bfactor_precision);
+    match format {
+        StructureFormat::Pdb => read_pdb(reader, options, builder),
+        StructureFormat::Mmcif => read_mmcif(reader, options, builder),
+    }
+}
@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+
+/// Compression schemes recognised from the leading bytes of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+}
+
+impl Compression {
+    /// Identifies the compression from magic bytes: `1f 8b` for gzip, `BZh` for bzip2.
+    pub fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if magic.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// File name suffixes added by compressors, stripped before looking at the format extension.
+const COMPRESSION_EXTENSIONS: [&str; 4] = ["gz", "bz2", "bzip2", "gzip"];
+
+/// Removes a trailing compression extension, so `1abc.cif.gz` becomes `1abc.cif`.
+pub fn strip_compression_extension(path: &Path) -> PathBuf {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if COMPRESSION_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()) => path.with_extension(""),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Wraps a reader so that gzip or bzip2 content is decompressed on the fly.
+///
+/// The compression is detected from the first bytes of the stream, which are
+/// peeked without being consumed; plain text is passed through untouched.
+pub fn decompress_reader<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    let compression = Compression::detect(reader.fill_buf()?);
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Compression::Bzip2 => Box::new(BufReader::new(MultiBzDecoder::new(reader))),
+    })
+}
+
+/// Opens a structure file, transparently decompressing it when needed.
+pub fn open_structure_file<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    decompress_reader(BufReader::new(file))
+}
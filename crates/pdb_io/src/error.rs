@@ -0,0 +1,85 @@
+use std::ops::Range;
+use std::path::PathBuf;
+
+use snafu::prelude::*;
+
+/// Errors raised while reading PDB or mmCIF input.
+///
+/// Line numbers start at 1. Column ranges are 0-based, end-exclusive byte
+/// offsets into the line and are displayed 1-based, as in the PDB specification.
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum PdbParseError {
+    #[snafu(display("Failed to open {}: {source}", path.display()))]
+    Open { path: PathBuf, source: std::io::Error },
+
+    #[snafu(display("Failed to read line {line}: {source}"))]
+    Read { line: usize, source: std::io::Error },
+
+    #[snafu(display(
+        "Line {line}: missing {field} (columns {}-{}): {content:?}",
+        columns.start + 1,
+        columns.end
+    ))]
+    MissingField {
+        line: usize,
+        field: &'static str,
+        columns: Range<usize>,
+        content: String,
+    },
+
+    #[snafu(display(
+        "Line {line}: failed to parse {field} from {value:?} (columns {}-{}): {content:?}",
+        columns.start + 1,
+        columns.end
+    ))]
+    InvalidField {
+        line: usize,
+        field: &'static str,
+        value: String,
+        columns: Range<usize>,
+        content: String,
+    },
+
+    #[snafu(display("Missing _atom_site.{tag} column in the mmCIF loop header"))]
+    MissingColumn { tag: String },
+
+    #[snafu(display("Line {line}: _atom_site loop ends in the middle of a row"))]
+    TruncatedLoop { line: usize },
+}
+
+impl PdbParseError {
+    /// Line the error was found on, when it relates to a specific record.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            PdbParseError::Read { line, .. }
+            | PdbParseError::MissingField { line, .. }
+            | PdbParseError::InvalidField { line, .. }
+            | PdbParseError::TruncatedLoop { line } => Some(*line),
+            PdbParseError::Open { .. } | PdbParseError::MissingColumn { .. } => None,
+        }
+    }
+}
+
+/// Errors raised while reading predicted aligned error JSON.
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+pub enum PaeError {
+    #[snafu(display("Failed to open {}: {source}", path.display()))]
+    OpenPae { path: PathBuf, source: std::io::Error },
+
+    #[snafu(display("Failed to read PAE JSON: {source}"))]
+    ReadPae { source: std::io::Error },
+
+    #[snafu(display("Invalid PAE JSON: {source}"))]
+    InvalidJson { source: serde_json::Error },
+
+    #[snafu(display("PAE JSON holds neither a predicted_aligned_error matrix nor residue1/residue2/distance lists"))]
+    MissingMatrix,
+
+    #[snafu(display("PAE row {row} has {len} values, expected {size}"))]
+    NotSquare { row: usize, len: usize, size: usize },
+
+    #[snafu(display("PAE lists differ in length: {residue1} residue1, {residue2} residue2 and {distance} distance values"))]
+    MismatchedLists { residue1: usize, residue2: usize, distance: usize },
+}
@@ -0,0 +1,162 @@
+use crate::{AtomCoordinate, Chain, Model, ParsedPDB};
+
+/// Borrowed `Structure -> Model -> Chain -> Residue -> Atom` view of a [`ParsedPDB`].
+///
+/// Residues are slices of consecutive atoms sharing a residue number and
+/// insertion code, so building the view copies no atom.
+#[derive(Debug, Clone)]
+pub struct StructureView<'a> {
+    pub models: Vec<ModelView<'a>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModelView<'a> {
+    pub model: &'a Model,
+    pub chains: Vec<ChainView<'a>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainView<'a> {
+    pub chain: &'a Chain,
+    pub residues: Vec<ResidueView<'a>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ResidueView<'a> {
+    /// Atoms of the residue, in file order; never empty
+    pub atoms: &'a [AtomCoordinate],
+}
+
+/// Backbone atoms of an amino acid; the carbonyl oxygen may be missing, e.g. in CA-only models.
+#[derive(Debug, Clone, Copy)]
+pub struct Backbone<'a> {
+    pub n: &'a AtomCoordinate,
+    pub ca: &'a AtomCoordinate,
+    pub c: &'a AtomCoordinate,
+    pub o: Option<&'a AtomCoordinate>,
+}
+
+impl ParsedPDB {
+    pub fn view(&self) -> StructureView<'_> {
+        StructureView { models: self.models.iter().map(Model::view).collect() }
+    }
+}
+
+impl Model {
+    pub fn view(&self) -> ModelView<'_> {
+        ModelView { model: self, chains: self.chains.iter().map(Chain::view).collect() }
+    }
+}
+
+impl Chain {
+    pub fn view(&self) -> ChainView<'_> {
+        let residues = self
+            .atoms
+            .chunk_by(|a, b| a.residue_seq == b.residue_seq && a.insertion_code == b.insertion_code)
+            .map(|atoms| ResidueView { atoms })
+            .collect();
+        ChainView { chain: self, residues }
+    }
+}
+
+impl<'a> StructureView<'a> {
+    pub fn residues(&self) -> impl Iterator<Item = &ResidueView<'a>> {
+        self.models.iter().flat_map(ModelView::residues)
+    }
+}
+
+impl<'a> ModelView<'a> {
+    pub fn residues(&self) -> impl Iterator<Item = &ResidueView<'a>> {
+        self.chains.iter().flat_map(|chain| chain.residues.iter())
+    }
+
+    /// Chains with the given identifier; HETATM-only chains may repeat a polymer chain ID.
+    pub fn chains_with_id<'s>(&'s self, chain_id: &'s str) -> impl Iterator<Item = &'s ChainView<'a>> {
+        self.chains.iter().filter(move |chain| chain.chain.id == chain_id)
+    }
+
+    /// Looks a residue up by chain identifier, residue number and insertion code.
+    pub fn residue(&self, chain_id: &str, residue_seq: i32, insertion_code: Option<char>) -> Option<&ResidueView<'a>> {
+        self.chains
+            .iter()
+            .filter(|chain| chain.chain.id == chain_id)
+            .find_map(|chain| chain.residue(residue_seq, insertion_code))
+    }
+
+    /// One alpha-carbon per residue, chain after chain.
+    pub fn ca_atoms(&self) -> Vec<&'a AtomCoordinate> {
+        self.residues().filter_map(ResidueView::ca).collect()
+    }
+
+    /// Backbone of every residue that has its N, CA and C atoms.
+    pub fn backbone(&self) -> impl Iterator<Item = Backbone<'a>> + '_ {
+        self.residues().filter_map(ResidueView::backbone)
+    }
+}
+
+impl<'a> ChainView<'a> {
+    pub fn id(&self) -> &'a str {
+        &self.chain.id
+    }
+
+    pub fn residue(&self, residue_seq: i32, insertion_code: Option<char>) -> Option<&ResidueView<'a>> {
+        self.residues
+            .iter()
+            .find(|residue| residue.residue_seq() == residue_seq && residue.insertion_code() == insertion_code)
+    }
+
+    pub fn ca_atoms(&self) -> Vec<&'a AtomCoordinate> {
+        self.residues.iter().filter_map(ResidueView::ca).collect()
+    }
+
+    pub fn backbone(&self) -> impl Iterator<Item = Backbone<'a>> + '_ {
+        self.residues.iter().filter_map(ResidueView::backbone)
+    }
+}
+
+impl<'a> ResidueView<'a> {
+    fn first(&self) -> &'a AtomCoordinate {
+        &self.atoms[0]
+    }
+
+    pub fn name(&self) -> &'a str {
+        &self.first().residue_name
+    }
+
+    pub fn chain_id(&self) -> &'a str {
+        &self.first().chain_id
+    }
+
+    pub fn residue_seq(&self) -> i32 {
+        self.first().residue_seq
+    }
+
+    pub fn insertion_code(&self) -> Option<char> {
+        self.first().insertion_code
+    }
+
+    /// Whether the residue comes from HETATM records (ligands, waters, modified residues).
+    pub fn is_hetatm(&self) -> bool {
+        self.atoms.iter().all(|atom| atom.is_hetatm)
+    }
+
+    /// First atom with the given name; with `AltLocPolicy::KeepAll` later conformers are ignored.
+    pub fn atom(&self, name: &str) -> Option<&'a AtomCoordinate> {
+        self.atoms.iter().find(|atom| atom.atom_name == name)
+    }
+
+    /// Alpha-carbon of the residue. HETATM alpha-carbons of modified
+    /// residues (MSE, SEP...) are kept, calcium ions (residue CA, atom CA) are not.
+    pub fn ca(&self) -> Option<&'a AtomCoordinate> {
+        self.atom("CA").filter(|atom| !(atom.is_hetatm && atom.residue_name == "CA"))
+    }
+
+    pub fn backbone(&self) -> Option<Backbone<'a>> {
+        Some(Backbone {
+            n: self.atom("N")?,
+            ca: self.ca()?,
+            c: self.atom("C")?,
+            o: self.atom("O"),
+        })
+    }
+}
@@ -1,118 +1,185 @@
-use std::error::Error;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+mod builder;
+mod compact;
+mod compression;
+mod error;
+mod hierarchy;
+mod mmcif;
+mod pae;
+mod pdb;
+mod sequence;
+mod writer;
+
+use std::io::{self, BufRead, Write};
 use std::path::Path;
 
-#[derive(Debug)]
+pub use compact::{
+    parse_structure_compact, parse_structure_compact_from_reader, BFactorPrecision, BFactors, CompactChain, CompactModel,
+    CompactPDB, NameId, NameTable,
+};
+pub use compression::{decompress_reader, open_structure_file, strip_compression_extension, Compression};
+pub use error::{PaeError, PdbParseError};
+pub use hierarchy::{Backbone, ChainView, ModelView, ResidueView, StructureView};
+pub use mmcif::{
+    parse_mmcif, parse_mmcif_from_reader, parse_mmcif_from_reader_with_options, parse_mmcif_str,
+    parse_mmcif_with_options,
+};
+pub use pae::{is_pae_file, pae_entry_id, read_pae, read_pae_from_reader, PredictedAlignedError};
+pub use pdb::{parse_pdb, parse_pdb_from_reader, parse_pdb_from_reader_with_options, parse_pdb_str, parse_pdb_with_options};
+pub use sequence::{align_to_seqres, one_letter_code, SeqresAlignment, SeqresChain};
+pub use writer::{write_mmcif, write_mmcif_with_bfactors, write_pdb, write_pdb_with_bfactors};
+
+#[derive(Debug, Clone)]
 pub struct AtomCoordinate {
     pub atom_name: String,
-    pub residue_seq: i32,
+    /// Alternate location indicator, `None` when the column is blank
+    pub alt_loc: Option<char>,
     pub residue_name: String,
+    /// Chain identifier, empty when the column is blank
+    pub chain_id: String,
+    pub residue_seq: i32,
+    /// Code for insertions of residues, `None` when the column is blank
+    pub insertion_code: Option<char>,
+    pub occupancy: f32,
     pub bfactor: f32,
+    /// Element symbol, inferred from the atom name when the column is missing
+    pub element: String,
+    /// Whether the atom comes from a HETATM record
+    pub is_hetatm: bool,
     pub x: f32,
     pub y: f32,
     pub z: f32,
 }
 
+#[derive(Debug, Clone)]
+pub struct Chain {
+    pub id: String,
+    pub atoms: Vec<AtomCoordinate>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Model {
+    /// Serial number from the MODEL record, 1 when the file has none
+    pub serial: i32,
+    pub chains: Vec<Chain>,
+}
+
+impl Model {
+    /// Iterates over every atom of the model, chain after chain.
+    pub fn atoms(&self) -> impl Iterator<Item = &AtomCoordinate> {
+        self.chains.iter().flat_map(|chain| chain.atoms.iter())
+    }
+}
+
 #[derive(Debug)]
 pub struct ParsedPDB {
-    pub atoms: Vec<AtomCoordinate>,
+    pub models: Vec<Model>,
+    /// Deposited sequences, empty when the file has no SEQRES records or `_pdbx_poly_seq_scheme` loop
+    pub seqres: Vec<SeqresChain>,
+    /// Records skipped in lenient mode, always empty otherwise
+    pub warnings: Vec<PdbParseError>,
+}
+
+impl ParsedPDB {
+    /// Iterates over every atom of every model.
+    pub fn atoms(&self) -> impl Iterator<Item = &AtomCoordinate> {
+        self.models.iter().flat_map(Model::atoms)
+    }
+}
+
+/// Which conformer to keep when an atom has several alternate locations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AltLocPolicy {
+    /// Keep the first alternate location encountered for each atom
+    #[default]
+    First,
+    /// Keep the alternate location with the highest occupancy (first one on ties)
+    HighestOccupancy,
+    /// Keep every alternate location
+    KeepAll,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub altloc: AltLocPolicy,
+    /// Skip malformed records and collect them as warnings instead of failing
+    pub lenient: bool,
+    /// B-factor storage of the compact parsers, ignored by the others
+    pub bfactor_precision: BFactorPrecision,
 }
 
 
-pub fn parse_pdb<P: AsRef<Path>>(pdb_path: P) -> Result<ParsedPDB, Box<dyn Error>> {
-
-    /*
-    1-4   "ATOM"                          left   character
-    7-11  Atom serial number               right  integer
-    13-16 Atom name                        left   * character
-    17    Alternate location indicator     character
-    18-20 Residue name                     right  character
-    22    Chain identifier                 character
-    23-26 Residue sequence number          right  integer
-    27    Code for insertions of residues  character
-    31-38 X orthogonal Angstrom coordinate right  floating
-    39-46 Y orthogonal Angstrom coordinate right  floating
-    47-54 Z orthogonal Angstrom coordinate right  floating
-    55-60 Occupancy                        right  floating
-    61-66 Temperature factor               right  floating
-    73-76 Segment identifier (optional)    left   character
-    77-78 Element symbol                   right  character
-    79-80 Charge (optional)                character
-
-    Taken from https://www.biostat.jhsph.edu/~iruczins/teaching/260.655/links/pdbformat.pdf
-    */
-
-
-    let file = File::open(pdb_path)?;
-    let reader = BufReader::new(file);
-
-    // Pre-allocate a vector for performance.
-    let mut atoms = Vec::with_capacity(10_000);
-
-    for line_result in reader.lines() {
-        let line = line_result?;
-
-        // Only process lines that start with "ATOM ".
-        if line.starts_with("ATOM ") {
-            // Parse the atom name (columns 13-16)
-            let atom_name = line.get(12..16)
-                .map(str::trim)
-                .ok_or_else(|| "Missing atom name".to_string())?
-                .to_string();
-
-            // Parse the residue name (columns 18-20)
-            let residue_name = line.get(17..20)
-                .map(str::trim)
-                .ok_or_else(|| "Missing residue name".to_string())?
-                .to_string();
-
-            // Parse the residue sequence number (columns 23-26)
-            let residue_seq = line.get(22..26)
-                .map(str::trim)
-                .ok_or_else(|| "Missing residue sequence".to_string())?
-                .parse::<i32>()
-                .map_err(|_| "Failed to parse residue sequence".to_string())?;
-
-            // Parse the X coordinate (columns 31-38)
-            let x = line.get(30..38)
-                .map(str::trim)
-                .ok_or_else(|| "Missing x coordinate".to_string())?
-                .parse::<f32>()
-                .map_err(|_| "Failed to parse x coordinate".to_string())?;
-
-            // Parse the Y coordinate (columns 39-46)
-            let y = line.get(38..46)
-                .map(str::trim)
-                .ok_or_else(|| "Missing y coordinate".to_string())?
-                .parse::<f32>()
-                .map_err(|_| "Failed to parse y coordinate".to_string())?;
-
-            // Parse the Z coordinate (columns 47-54)
-            let z = line.get(46..54)
-                .map(str::trim)
-                .ok_or_else(|| "Missing z coordinate".to_string())?
-                .parse::<f32>()
-                .map_err(|_| "Failed to parse z coordinate".to_string())?;
-
-            // Parse the B-factor (columns 61-66)
-            let bfactor = line.get(60..66)
-                .map(str::trim)
-                .ok_or_else(|| "Missing B-factor".to_string())?
-                .parse::<f32>()
-                .map_err(|_| "Failed to parse B-factor".to_string())?;
-
-            atoms.push(AtomCoordinate {
-                atom_name,
-                residue_seq,
-                residue_name,
-                bfactor,
-                x,
-                y,
-                z,
-            });
+/// Coordinate file formats understood by [`parse_structure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructureFormat {
+    Pdb,
+    Mmcif,
+}
+
+impl StructureFormat {
+    /// Recognises `.pdb` / `.ent` and `.cif` / `.mmcif`, optionally followed by
+    /// a compression suffix such as `.gz` or `.bz2`.
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = strip_compression_extension(path.as_ref())
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("pdb") | Some("ent") => Some(StructureFormat::Pdb),
+            Some("cif") | Some("mmcif") => Some(StructureFormat::Mmcif),
+            _ => None,
         }
     }
 
-    Ok(ParsedPDB { atoms })
-}  
\ No newline at end of file
+    /// Guesses the format from the file extension, defaulting to PDB when it is not recognised.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        Self::from_extension(path).unwrap_or(StructureFormat::Pdb)
+    }
+}
+
+/// Entry identifier of a structure file: the file name without its format and
+/// compression extensions, so `AF-P12345-F1-model_v4.cif.gz` gives `AF-P12345-F1-model_v4`.
+pub fn entry_id<P: AsRef<Path>>(path: P) -> Option<String> {
+    let path = strip_compression_extension(path.as_ref());
+    let stem = match StructureFormat::from_extension(&path) {
+        Some(_) => path.file_stem(),
+        None => path.file_name(),
+    };
+    stem.and_then(|s| s.to_str()).map(str::to_string)
+}
+
+/// Parses a PDB or mmCIF file, picking the reader from the file extension.
+/// Gzip and bzip2 compressed files are decompressed transparently.
+pub fn parse_structure<P: AsRef<Path>>(path: P) -> Result<ParsedPDB, PdbParseError> {
+    parse_structure_with_options(path, &ParseOptions::default())
+}
+
+pub fn parse_structure_with_options<P: AsRef<Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<ParsedPDB, PdbParseError> {
+    match StructureFormat::from_path(&path) {
+        StructureFormat::Pdb => parse_pdb_with_options(path, options),
+        StructureFormat::Mmcif => parse_mmcif_with_options(path, options),
+    }
+}
+
+/// Parses a structure from any reader, e.g. an archive member or a byte buffer.
+/// Gzip and bzip2 content is detected from its magic bytes and decompressed.
+pub fn parse_structure_from_reader<R: BufRead>(
+    reader: R,
+    format: StructureFormat,
+    options: &ParseOptions,
+) -> Result<ParsedPDB, PdbParseError> {
+    match format {
+        StructureFormat::Pdb => parse_pdb_from_reader_with_options(reader, options),
+        StructureFormat::Mmcif => parse_mmcif_from_reader_with_options(reader, options),
+    }
+}
+
+/// Writes a structure in the given format; the counterpart of [`parse_structure_from_reader`].
+pub fn write_structure<W: Write>(pdb: &ParsedPDB, writer: W, format: StructureFormat) -> io::Result<()> {
+    match format {
+        StructureFormat::Pdb => write_pdb(pdb, writer),
+        StructureFormat::Mmcif => write_mmcif(pdb, writer),
+    }
+}
@@ -0,0 +1,457 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+
+use snafu::prelude::*;
+
+use crate::builder::{AtomRecord, AtomSink, StructureBuilder};
+use crate::compression::decompress_reader;
+use crate::error::{
+    InvalidFieldSnafu, MissingColumnSnafu, MissingFieldSnafu, OpenSnafu, PdbParseError, ReadSnafu, TruncatedLoopSnafu,
+};
+use crate::pdb::infer_element;
+use crate::sequence::push_seqres;
+use crate::{ParseOptions, ParsedPDB, SeqresChain};
+
+/// A whitespace-delimited CIF value. Quoted values are never keywords nor nulls.
+struct CifToken<'a> {
+    text: &'a str,
+    quoted: bool,
+    /// Byte offset of the value within its line
+    start: usize,
+}
+
+impl CifToken<'_> {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        !self.quoted && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    fn is_tag(&self) -> bool {
+        !self.quoted && self.text.starts_with('_')
+    }
+
+    fn is_block_start(&self) -> bool {
+        !self.quoted && {
+            let lower = self.text.to_ascii_lowercase();
+            lower.starts_with("data_") || lower.starts_with("save_") || lower == "global_"
+        }
+    }
+}
+
+/// Splits one line of CIF text into tokens, honouring quotes and `#` comments.
+fn tokenize_line(line: &str) -> Vec<CifToken<'_>> {
+    let bytes = line.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if c == b'#' {
+            break;
+        } else if c == b'\'' || c == b'"' {
+            // A quote only closes the value when followed by whitespace or the end of line.
+            let start = i + 1;
+            let mut end = start;
+            while end < bytes.len()
+                && !(bytes[end] == c && (end + 1 == bytes.len() || bytes[end + 1].is_ascii_whitespace()))
+            {
+                end += 1;
+            }
+            tokens.push(CifToken { text: &line[start..end.min(bytes.len())], quoted: true, start });
+            i = end + 1;
+        } else {
+            let start = i;
+            while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            tokens.push(CifToken { text: &line[start..i], quoted: false, start });
+        }
+    }
+
+    tokens
+}
+
+/// Column positions of the `_atom_site` items we read, resolved from the loop header.
+struct AtomSiteColumns {
+    group: Option<usize>,
+    type_symbol: Option<usize>,
+    atom_id: usize,
+    alt_id: Option<usize>,
+    comp_id: usize,
+    asym_id: usize,
+    seq_id: usize,
+    ins_code: Option<usize>,
+    x: usize,
+    y: usize,
+    z: usize,
+    occupancy: Option<usize>,
+    b_iso: Option<usize>,
+    model_num: Option<usize>,
+}
+
+impl AtomSiteColumns {
+    fn from_tags(tags: &[String]) -> Result<Self, PdbParseError> {
+        let find = |name: &str| {
+            tags.iter()
+                .position(|t| t["_atom_site.".len()..].eq_ignore_ascii_case(name))
+        };
+        // Author-provided identifiers match the legacy PDB columns, label_* is the fallback.
+        let either = |auth: &str, label: &str| {
+            find(auth)
+                .or_else(|| find(label))
+                .context(MissingColumnSnafu { tag: auth })
+        };
+        let required = |name: &str| find(name).context(MissingColumnSnafu { tag: name });
+
+        Ok(AtomSiteColumns {
+            group: find("group_PDB"),
+            type_symbol: find("type_symbol"),
+            atom_id: either("auth_atom_id", "label_atom_id")?,
+            alt_id: find("label_alt_id"),
+            comp_id: either("auth_comp_id", "label_comp_id")?,
+            asym_id: either("auth_asym_id", "label_asym_id")?,
+            seq_id: either("auth_seq_id", "label_seq_id")?,
+            ins_code: find("pdbx_PDB_ins_code"),
+            x: required("Cartn_x")?,
+            y: required("Cartn_y")?,
+            z: required("Cartn_z")?,
+            occupancy: find("occupancy"),
+            b_iso: find("B_iso_or_equiv"),
+            model_num: find("pdbx_PDB_model_num"),
+        })
+    }
+}
+
+/// Column positions of the `_pdbx_poly_seq_scheme` items holding the deposited sequence.
+struct PolySeqColumns {
+    chain: usize,
+    residue: usize,
+}
+
+impl PolySeqColumns {
+    /// The author chain matches `auth_asym_id` in `_atom_site`; `None` when the loop lacks the items.
+    fn from_tags(tags: &[String]) -> Option<Self> {
+        let find = |name: &str| {
+            tags.iter()
+                .position(|t| t["_pdbx_poly_seq_scheme.".len()..].eq_ignore_ascii_case(name))
+        };
+        Some(PolySeqColumns {
+            chain: find("pdb_strand_id").or_else(|| find("asym_id"))?,
+            residue: find("mon_id")?,
+        })
+    }
+}
+
+/// One value of an `_atom_site` row, with its position for error reporting.
+struct CifValue {
+    text: String,
+    quoted: bool,
+    line: usize,
+    columns: Range<usize>,
+}
+
+/// A complete `_atom_site` row.
+struct AtomSiteRow<'r>(&'r [CifValue]);
+
+impl<'r> AtomSiteRow<'r> {
+    /// Returns `None` for the CIF null values `.` and `?`.
+    fn value(&self, column: usize) -> Option<&'r str> {
+        let value = &self.0[column];
+        if !value.quoted && (value.text == "." || value.text == "?") {
+            None
+        } else {
+            Some(value.text.as_str())
+        }
+    }
+
+    fn optional_char(&self, column: Option<usize>) -> Option<char> {
+        column
+            .and_then(|c| self.value(c))
+            .and_then(|v| v.chars().next())
+    }
+
+    /// The row as it appears in the file, used as the raw content of errors.
+    fn content(&self) -> String {
+        self.0.iter().map(|v| v.text.as_str()).collect::<Vec<_>>().join(" ")
+    }
+
+    fn required(&self, column: usize, field: &'static str) -> Result<&'r str, PdbParseError> {
+        let value = &self.0[column];
+        self.value(column).context(MissingFieldSnafu {
+            line: value.line,
+            field,
+            columns: value.columns.clone(),
+            content: self.content(),
+        })
+    }
+
+    fn number<T: FromStr>(&self, column: usize, field: &'static str) -> Result<T, PdbParseError> {
+        let text = self.required(column, field)?;
+        let value = &self.0[column];
+        text.parse::<T>().ok().context(InvalidFieldSnafu {
+            line: value.line,
+            field,
+            value: text,
+            columns: value.columns.clone(),
+            content: self.content(),
+        })
+    }
+
+    fn to_atom(&self, columns: &AtomSiteColumns) -> Result<AtomRecord<'r>, PdbParseError> {
+        let atom_name = self.required(columns.atom_id, "atom name")?;
+        let residue_name = self.required(columns.comp_id, "residue name")?;
+        let chain_id = self.value(columns.asym_id).unwrap_or_default();
+
+        let element = columns
+            .type_symbol
+            .and_then(|c| self.value(c))
+            .unwrap_or_else(|| infer_element(atom_name));
+
+        let occupancy = match columns.occupancy {
+            Some(c) => self.number(c, "occupancy")?,
+            None => 1.0,
+        };
+
+        let bfactor = match columns.b_iso {
+            Some(c) => self.number(c, "B-factor")?,
+            None => 0.0,
+        };
+
+        Ok(AtomRecord {
+            alt_loc: self.optional_char(columns.alt_id),
+            residue_seq: self.number(columns.seq_id, "residue sequence")?,
+            insertion_code: self.optional_char(columns.ins_code),
+            is_hetatm: columns.group.and_then(|c| self.value(c)) == Some("HETATM"),
+            x: self.number(columns.x, "x coordinate")?,
+            y: self.number(columns.y, "y coordinate")?,
+            z: self.number(columns.z, "z coordinate")?,
+            atom_name,
+            residue_name,
+            chain_id,
+            occupancy,
+            bfactor,
+            element,
+        })
+    }
+}
+
+enum LoopState {
+    /// Outside of any loop, or inside a loop we do not care about
+    Skipping,
+    /// Reading the tags that follow a `loop_` keyword
+    Header(Vec<String>),
+    /// Reading the values of the `_atom_site` loop
+    AtomSite(AtomSiteColumns, usize),
+    /// Reading the values of the `_pdbx_poly_seq_scheme` loop
+    PolySeq(PolySeqColumns, usize),
+}
+
+/// Parses the `_atom_site` loop of an mmCIF / PDBx file into the same model as [`parse_pdb`](crate::parse_pdb).
+pub fn parse_mmcif<P: AsRef<Path>>(cif_path: P) -> Result<ParsedPDB, PdbParseError> {
+    parse_mmcif_with_options(cif_path, &ParseOptions::default())
+}
+
+pub fn parse_mmcif_with_options<P: AsRef<Path>>(
+    cif_path: P,
+    options: &ParseOptions,
+) -> Result<ParsedPDB, PdbParseError> {
+    let cif_path = cif_path.as_ref();
+    let file = File::open(cif_path).context(OpenSnafu { path: cif_path })?;
+    parse_mmcif_from_reader_with_options(BufReader::new(file), options)
+}
+
+/// Parses from any buffered reader, e.g. stdin or an archive member.
+/// Gzip and bzip2 content is decompressed transparently.
+pub fn parse_mmcif_from_reader<R: BufRead>(reader: R) -> Result<ParsedPDB, PdbParseError> {
+    parse_mmcif_from_reader_with_options(reader, &ParseOptions::default())
+}
+
+pub fn parse_mmcif_from_reader_with_options<R: BufRead>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<ParsedPDB, PdbParseError> {
+    let reader = decompress_reader(reader).context(ReadSnafu { line: 1usize })?;
+    read_mmcif(reader, options, StructureBuilder::new())
+}
+
+/// Parses mmCIF text held in memory.
+pub fn parse_mmcif_str(text: &str) -> Result<ParsedPDB, PdbParseError> {
+    parse_mmcif_from_reader(text.as_bytes())
+}
+
+/// Parses already opened, uncompressed text into the representation built by `builder`.
+pub(crate) fn read_mmcif<R: BufRead, S: AtomSink>(
+    reader: R,
+    options: &ParseOptions,
+    builder: S,
+) -> Result<S::Output, PdbParseError> {
+
+    /*
+    Only the `_atom_site` category and the deposited sequence from
+    `_pdbx_poly_seq_scheme` are read. Chain, residue number and atom name
+    use the auth_* items so that residues are numbered as in the PDB format,
+    falling back to label_* when the author items are absent. Models are split
+    on `pdbx_PDB_model_num`.
+
+    See https://mmcif.wwpdb.org/dictionaries/mmcif_pdbx_v50.dic/Categories/atom_site.html
+    */
+
+    let mut site_reader = AtomSiteReader {
+        state: LoopState::Skipping,
+        row: Vec::new(),
+        filled: 0,
+        builder,
+        current_model: None,
+        seqres: Vec::new(),
+        warnings: Vec::new(),
+        lenient: options.lenient,
+    };
+    // Semicolon-delimited text field being accumulated, with its first line number.
+    let mut text_field: Option<(String, usize)> = None;
+    let mut line_number = 0;
+
+    for (index, line_result) in reader.lines().enumerate() {
+        line_number = index + 1;
+        let line = line_result.context(ReadSnafu { line: line_number })?;
+
+        // Semicolon-delimited text fields span several lines and form a single value.
+        if let Some((field, _)) = text_field.as_mut() {
+            if line.starts_with(';') {
+                let (field, first_line) = text_field.take().unwrap();
+                site_reader.push_value(&field, true, first_line, 0..1)?;
+            } else {
+                field.push_str(&line);
+                field.push('\n');
+            }
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix(';') {
+            text_field = Some((format!("{}\n", rest), line_number));
+            continue;
+        }
+
+        for token in tokenize_line(&line) {
+            if token.is_keyword("loop_") {
+                site_reader.state = LoopState::Header(Vec::new());
+                continue;
+            }
+            if token.is_tag() || token.is_block_start() {
+                site_reader.state = match std::mem::replace(&mut site_reader.state, LoopState::Skipping) {
+                    LoopState::Header(mut tags) if token.is_tag() => {
+                        tags.push(token.text.to_string());
+                        LoopState::Header(tags)
+                    }
+                    _ => LoopState::Skipping,
+                };
+                continue;
+            }
+            let columns = token.start..token.start + token.text.len();
+            site_reader.push_value(token.text, token.quoted, line_number, columns)?;
+        }
+    }
+
+    if site_reader.filled != 0 {
+        let error = TruncatedLoopSnafu { line: line_number }.build();
+        if !options.lenient {
+            return Err(error);
+        }
+        site_reader.warnings.push(error);
+    }
+
+    Ok(site_reader.builder.finish(options.altloc, site_reader.seqres, site_reader.warnings))
+}
+
+/// Loop state machine turning `_atom_site` values into atoms.
+struct AtomSiteReader<S> {
+    state: LoopState,
+    /// Values of the current row; the first `filled` are set, buffers are reused across rows
+    row: Vec<CifValue>,
+    filled: usize,
+    builder: S,
+    current_model: Option<i32>,
+    seqres: Vec<SeqresChain>,
+    warnings: Vec<PdbParseError>,
+    lenient: bool,
+}
+
+impl<S: AtomSink> AtomSiteReader<S> {
+    /// Feeds one value, emitting an atom once a row is complete.
+    fn push_value(&mut self, text: &str, quoted: bool, line: usize, columns: Range<usize>) -> Result<(), PdbParseError> {
+        // The first value after a loop header decides whether this is a loop we want.
+        if let LoopState::Header(tags) = &self.state {
+            let in_category = |category: &str| !tags.is_empty() && tags.iter().all(|t| t.to_ascii_lowercase().starts_with(category));
+            self.state = if in_category("_atom_site.") {
+                LoopState::AtomSite(AtomSiteColumns::from_tags(tags)?, tags.len())
+            } else if in_category("_pdbx_poly_seq_scheme.") {
+                PolySeqColumns::from_tags(tags).map_or(LoopState::Skipping, |c| LoopState::PolySeq(c, tags.len()))
+            } else {
+                LoopState::Skipping
+            };
+        }
+
+        let width = match &self.state {
+            LoopState::AtomSite(_, width) | LoopState::PolySeq(_, width) => *width,
+            LoopState::Skipping | LoopState::Header(_) => return Ok(()),
+        };
+
+        match self.row.get_mut(self.filled) {
+            Some(value) => {
+                value.text.clear();
+                value.text.push_str(text);
+                value.quoted = quoted;
+                value.line = line;
+                value.columns = columns;
+            }
+            None => self.row.push(CifValue { text: text.to_string(), quoted, line, columns }),
+        }
+        self.filled += 1;
+        if self.filled < width {
+            return Ok(());
+        }
+
+        let row = AtomSiteRow(&self.row[..width]);
+        self.filled = 0;
+        let record = match &self.state {
+            LoopState::AtomSite(site_columns, _) => push_row(row, site_columns, &mut self.builder, &mut self.current_model),
+            LoopState::PolySeq(seq_columns, _) => {
+                let chain_id = row.value(seq_columns.chain).unwrap_or_default();
+                if let Some(residue) = row.value(seq_columns.residue) {
+                    push_seqres(&mut self.seqres, chain_id, residue);
+                }
+                Ok(())
+            }
+            LoopState::Skipping | LoopState::Header(_) => unreachable!("only data loops collect rows"),
+        };
+        match record {
+            Err(error) if self.lenient => self.warnings.push(error),
+            other => other?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Converts a complete row and hands the atom to the builder, opening a new model when needed.
+fn push_row<S: AtomSink>(
+    row: AtomSiteRow<'_>,
+    columns: &AtomSiteColumns,
+    builder: &mut S,
+    current_model: &mut Option<i32>,
+) -> Result<(), PdbParseError> {
+    let model = match columns.model_num {
+        Some(c) => row.number(c, "model number")?,
+        None => 1,
+    };
+    let atom = row.to_atom(columns)?;
+
+    if *current_model != Some(model) {
+        builder.start_model(model);
+        *current_model = Some(model);
+    }
+    builder.push_atom(atom);
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use serde::Deserialize;
+use snafu::prelude::*;
+
+use crate::compression::{decompress_reader, strip_compression_extension};
+use crate::error::{
+    InvalidJsonSnafu, MismatchedListsSnafu, MissingMatrixSnafu, NotSquareSnafu, OpenPaeSnafu, PaeError, ReadPaeSnafu,
+};
+
+/// Predicted aligned error of a model, in A.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PredictedAlignedError {
+    /// Number of residues, over all chains in file order
+    pub size: usize,
+    /// Row-major `size x size` values: expected error at residue j when aligned on residue i
+    pub values: Vec<f32>,
+    /// Upper bound of the values reported by the predictor, when present
+    pub max: Option<f32>,
+}
+
+impl PredictedAlignedError {
+    /// Error at residue `j` (0-based) when the model is aligned on residue `i`.
+    pub fn get(&self, i: usize, j: usize) -> f32 {
+        self.values[i * self.size + j]
+    }
+
+    /// Errors between the given residues only, in the order given, e.g. for one domain.
+    pub fn submatrix(&self, indices: &[usize]) -> PredictedAlignedError {
+        let values = indices.iter().flat_map(|&i| indices.iter().map(move |&j| self.get(i, j))).collect();
+        PredictedAlignedError { size: indices.len(), values, max: self.max }
+    }
+}
+
+/// One record of the JSON: AlphaFold DB v3+ and ColabFold store a matrix,
+/// AlphaFold DB v1/v2 flat lists of 1-based residue pairs.
+#[derive(Deserialize)]
+struct PaeRecord {
+    #[serde(alias = "pae")]
+    predicted_aligned_error: Option<Vec<Vec<f32>>>,
+    residue1: Option<Vec<usize>>,
+    residue2: Option<Vec<usize>>,
+    distance: Option<Vec<f32>>,
+    #[serde(alias = "max_pae")]
+    max_predicted_aligned_error: Option<f32>,
+}
+
+impl PaeRecord {
+    fn into_pae(self) -> Result<PredictedAlignedError, PaeError> {
+        let max = self.max_predicted_aligned_error;
+        if let Some(rows) = self.predicted_aligned_error {
+            let size = rows.len();
+            let mut values = Vec::with_capacity(size * size);
+            for (row, line) in rows.into_iter().enumerate() {
+                ensure!(line.len() == size, NotSquareSnafu { row, len: line.len(), size });
+                values.extend(line);
+            }
+            return Ok(PredictedAlignedError { size, values, max });
+        }
+
+        let (Some(residue1), Some(residue2), Some(distance)) = (self.residue1, self.residue2, self.distance) else {
+            return MissingMatrixSnafu.fail();
+        };
+        ensure!(
+            residue1.len() == residue2.len() && residue2.len() == distance.len(),
+            MismatchedListsSnafu { residue1: residue1.len(), residue2: residue2.len(), distance: distance.len() }
+        );
+        let size = residue1.iter().chain(&residue2).copied().max().unwrap_or(0);
+        let mut values = vec![0.0; size * size];
+        for ((&i, &j), &d) in residue1.iter().zip(&residue2).zip(&distance) {
+            if i > 0 && j > 0 {
+                values[(i - 1) * size + (j - 1)] = d;
+            }
+        }
+        Ok(PredictedAlignedError { size, values, max })
+    }
+}
+
+/// Reads predicted aligned error JSON from any reader; gzip and bzip2 content is decompressed.
+///
+/// Accepts a single record or a list of records, of which the first is used.
+pub fn read_pae_from_reader<R: BufRead>(reader: R) -> Result<PredictedAlignedError, PaeError> {
+    let mut bytes = Vec::new();
+    decompress_reader(reader).and_then(|mut r| r.read_to_end(&mut bytes)).context(ReadPaeSnafu)?;
+
+    let record = match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'[') => {
+            let records: Vec<PaeRecord> = serde_json::from_slice(&bytes).context(InvalidJsonSnafu)?;
+            records.into_iter().next().context(MissingMatrixSnafu)?
+        }
+        _ => serde_json::from_slice(&bytes).context(InvalidJsonSnafu)?,
+    };
+    record.into_pae()
+}
+
+/// Reads a predicted aligned error JSON file, possibly compressed.
+pub fn read_pae<P: AsRef<Path>>(path: P) -> Result<PredictedAlignedError, PaeError> {
+    let path = path.as_ref();
+    let file = File::open(path).context(OpenPaeSnafu { path })?;
+    read_pae_from_reader(BufReader::new(file))
+}
+
+/// Whether the path looks like a PAE file: `.json`, possibly compressed, named
+/// `*predicted_aligned_error*` (AlphaFold DB) or ending in `pae`.
+pub fn is_pae_file<P: AsRef<Path>>(path: P) -> bool {
+    let path = strip_compression_extension(path.as_ref());
+    let is_json = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    is_json && (stem.contains("predicted_aligned_error") || stem.to_ascii_lowercase().ends_with("pae"))
+}
+
+/// Entry identifier of the structure a PAE file belongs to, comparable with [`crate::entry_id`]:
+/// `AF-P12345-F1-predicted_aligned_error_v4.json.gz` gives `AF-P12345-F1-model_v4`
+/// and `1abc_pae.json` gives `1abc`.
+pub fn pae_entry_id<P: AsRef<Path>>(path: P) -> Option<String> {
+    let path = strip_compression_extension(path.as_ref());
+    let stem = path.file_stem()?.to_str()?;
+    if stem.contains("predicted_aligned_error") {
+        return Some(stem.replacen("predicted_aligned_error", "model", 1));
+    }
+    let trimmed = stem.strip_suffix("pae").or_else(|| stem.strip_suffix("PAE")).unwrap_or(stem);
+    let id = trimmed.trim_end_matches(['_', '-', '.']);
+    (!id.is_empty()).then(|| id.to_string())
+}
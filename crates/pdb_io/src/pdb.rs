@@ -0,0 +1,230 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+
+use snafu::prelude::*;
+
+use crate::builder::{AtomRecord, AtomSink, StructureBuilder};
+use crate::compression::decompress_reader;
+use crate::error::{InvalidFieldSnafu, MissingFieldSnafu, OpenSnafu, PdbParseError, ReadSnafu};
+use crate::sequence::push_seqres;
+use crate::{ParseOptions, ParsedPDB, SeqresChain};
+
+pub fn parse_pdb<P: AsRef<Path>>(pdb_path: P) -> Result<ParsedPDB, PdbParseError> {
+    parse_pdb_with_options(pdb_path, &ParseOptions::default())
+}
+
+pub fn parse_pdb_with_options<P: AsRef<Path>>(
+    pdb_path: P,
+    options: &ParseOptions,
+) -> Result<ParsedPDB, PdbParseError> {
+    let pdb_path = pdb_path.as_ref();
+    let file = File::open(pdb_path).context(OpenSnafu { path: pdb_path })?;
+    parse_pdb_from_reader_with_options(BufReader::new(file), options)
+}
+
+/// Parses from any buffered reader, e.g. stdin or an archive member.
+/// Gzip and bzip2 content is decompressed transparently.
+pub fn parse_pdb_from_reader<R: BufRead>(reader: R) -> Result<ParsedPDB, PdbParseError> {
+    parse_pdb_from_reader_with_options(reader, &ParseOptions::default())
+}
+
+pub fn parse_pdb_from_reader_with_options<R: BufRead>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<ParsedPDB, PdbParseError> {
+    let reader = decompress_reader(reader).context(ReadSnafu { line: 1usize })?;
+    read_pdb(reader, options, StructureBuilder::new())
+}
+
+/// Parses PDB text held in memory.
+pub fn parse_pdb_str(text: &str) -> Result<ParsedPDB, PdbParseError> {
+    parse_pdb_from_reader(text.as_bytes())
+}
+
+/// Parses already opened, uncompressed text into the representation built by `builder`.
+///
+/// In lenient mode a malformed record is skipped and reported in
+/// [`ParsedPDB::warnings`] instead of aborting the whole file.
+pub(crate) fn read_pdb<R: BufRead, S: AtomSink>(
+    mut reader: R,
+    options: &ParseOptions,
+    mut builder: S,
+) -> Result<S::Output, PdbParseError> {
+
+    /*
+    1-4   "ATOM"                          left   character
+    7-11  Atom serial number               right  integer
+    13-16 Atom name                        left   * character
+    17    Alternate location indicator     character
+    18-20 Residue name                     right  character
+    22    Chain identifier                 character
+    23-26 Residue sequence number          right  integer
+    27    Code for insertions of residues  character
+    31-38 X orthogonal Angstrom coordinate right  floating
+    39-46 Y orthogonal Angstrom coordinate right  floating
+    47-54 Z orthogonal Angstrom coordinate right  floating
+    55-60 Occupancy                        right  floating
+    61-66 Temperature factor               right  floating
+    73-76 Segment identifier (optional)    left   character
+    77-78 Element symbol                   right  character
+    79-80 Charge (optional)                character
+
+    Taken from https://www.biostat.jhsph.edu/~iruczins/teaching/260.655/links/pdbformat.pdf
+    */
+
+    let mut warnings = Vec::new();
+    let mut seqres = Vec::new();
+    // A single line buffer is reused, records borrow their text fields from it.
+    let mut line = String::new();
+    let mut line_number = 0;
+
+    loop {
+        line_number += 1;
+        line.clear();
+        let bytes = reader.read_line(&mut line).context(ReadSnafu { line: line_number })?;
+        if bytes == 0 {
+            break;
+        }
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        let record = if line.starts_with("ATOM ") {
+            parse_atom_line(line, line_number, false).map(|atom| builder.push_atom(atom))
+        } else if line.starts_with("HETATM") {
+            parse_atom_line(line, line_number, true).map(|atom| builder.push_atom(atom))
+        } else if line.starts_with("MODEL ") {
+            // Parse the model serial number (columns 11-14)
+            number_field::<i32>(line, line_number, 10..14, "model serial number")
+                .map(|serial| builder.start_model(serial))
+        } else if line.starts_with("SEQRES") {
+            parse_seqres_line(line, line_number, &mut seqres)
+        } else if line.starts_with("ENDMDL") {
+            builder.end_model();
+            Ok(())
+        } else if line.starts_with("TER") {
+            builder.terminate_chain();
+            Ok(())
+        } else {
+            Ok(())
+        };
+
+        match record {
+            Err(error) if options.lenient => warnings.push(error),
+            other => other?,
+        }
+    }
+
+    Ok(builder.finish(options.altloc, seqres, warnings))
+}
+
+/// Reads a trimmed text field, failing when the line is too short to hold it.
+fn text_field<'l>(
+    line: &'l str,
+    line_number: usize,
+    columns: Range<usize>,
+    field: &'static str,
+) -> Result<&'l str, PdbParseError> {
+    line.get(columns.clone())
+        .map(str::trim)
+        .context(MissingFieldSnafu { line: line_number, field, columns, content: line })
+}
+
+/// Reads and parses a numeric field.
+fn number_field<T: FromStr>(
+    line: &str,
+    line_number: usize,
+    columns: Range<usize>,
+    field: &'static str,
+) -> Result<T, PdbParseError> {
+    let value = text_field(line, line_number, columns.clone(), field)?;
+    value.parse::<T>().ok().context(InvalidFieldSnafu {
+        line: line_number,
+        field,
+        value,
+        columns,
+        content: line,
+    })
+}
+
+/// Parses the fixed columns of an ATOM or HETATM record.
+fn parse_atom_line(line: &str, line_number: usize, is_hetatm: bool) -> Result<AtomRecord<'_>, PdbParseError> {
+    // Parse the atom name (columns 13-16)
+    let atom_name = text_field(line, line_number, 12..16, "atom name")?;
+
+    // Parse the alternate location indicator (column 17)
+    let alt_loc = parse_flag(line, 16);
+
+    // Parse the residue name (columns 18-20)
+    let residue_name = text_field(line, line_number, 17..20, "residue name")?;
+
+    // Parse the chain identifier (column 22)
+    let chain_id = text_field(line, line_number, 21..22, "chain identifier")?;
+
+    // Parse the residue sequence number (columns 23-26)
+    let residue_seq = number_field(line, line_number, 22..26, "residue sequence")?;
+
+    // Parse the insertion code (column 27)
+    let insertion_code = parse_flag(line, 26);
+
+    // Parse the X, Y and Z coordinates (columns 31-38, 39-46, 47-54)
+    let x = number_field(line, line_number, 30..38, "x coordinate")?;
+    let y = number_field(line, line_number, 38..46, "y coordinate")?;
+    let z = number_field(line, line_number, 46..54, "z coordinate")?;
+
+    // Parse the occupancy (columns 55-60)
+    let occupancy = number_field(line, line_number, 54..60, "occupancy")?;
+
+    // Parse the B-factor (columns 61-66)
+    let bfactor = number_field(line, line_number, 60..66, "B-factor")?;
+
+    // Parse the element symbol (columns 77-78), optional in older files
+    let element = line.get(76..78)
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .unwrap_or_else(|| infer_element(atom_name));
+
+    Ok(AtomRecord {
+        atom_name,
+        alt_loc,
+        residue_name,
+        chain_id,
+        residue_seq,
+        insertion_code,
+        occupancy,
+        bfactor,
+        element,
+        is_hetatm,
+        x,
+        y,
+        z,
+    })
+}
+
+/// Appends the residue names of a SEQRES record (chain in column 12, up to 13 names from column 20).
+fn parse_seqres_line(line: &str, line_number: usize, seqres: &mut Vec<SeqresChain>) -> Result<(), PdbParseError> {
+    let chain_id = text_field(line, line_number, 11..12, "chain identifier")?;
+    for k in 0..13 {
+        let start = 19 + 4 * k;
+        match line.get(start..start + 3).map(str::trim) {
+            Some(name) if !name.is_empty() => push_seqres(seqres, chain_id, name),
+            _ => break,
+        }
+    }
+    Ok(())
+}
+
+/// Reads a single-character column, blank meaning absent.
+fn parse_flag(line: &str, column: usize) -> Option<char> {
+    line.get(column..column + 1)
+        .and_then(|c| c.chars().next())
+        .filter(|c| !c.is_whitespace())
+}
+
+/// Best-effort element guess from the atom name, used when columns 77-78 are missing.
+pub(crate) fn infer_element(atom_name: &str) -> &str {
+    atom_name
+        .find(|c: char| c.is_ascii_alphabetic())
+        .map_or("", |i| &atom_name[i..i + 1])
+}
@@ -0,0 +1,194 @@
+use crate::hierarchy::{ChainView, ModelView, ResidueView};
+use crate::ParsedPDB;
+
+/// Deposited sequence of one chain, from SEQRES records or the mmCIF `_pdbx_poly_seq_scheme` loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeqresChain {
+    pub chain_id: String,
+    /// Three-letter residue names
+    pub residues: Vec<String>,
+}
+
+impl SeqresChain {
+    /// One-letter sequence, `X` for unknown residues.
+    pub fn sequence(&self) -> String {
+        self.residues.iter().map(|name| one_letter_code(name).unwrap_or('X')).collect()
+    }
+}
+
+/// Appends a residue to the deposited sequence of its chain.
+pub(crate) fn push_seqres(seqres: &mut Vec<SeqresChain>, chain_id: &str, residue_name: &str) {
+    match seqres.iter_mut().find(|chain| chain.chain_id == chain_id) {
+        Some(chain) => chain.residues.push(residue_name.to_string()),
+        None => seqres.push(SeqresChain { chain_id: chain_id.to_string(), residues: vec![residue_name.to_string()] }),
+    }
+}
+
+/// One-letter code of a standard or common modified amino acid.
+///
+/// Modified residues map to their parent amino acid (MSE -> M, SEP -> S...).
+pub fn one_letter_code(residue_name: &str) -> Option<char> {
+    let code = match residue_name {
+        "ALA" => 'A',
+        "ARG" => 'R',
+        "ASN" => 'N',
+        "ASP" => 'D',
+        "CYS" => 'C',
+        "GLN" => 'Q',
+        "GLU" => 'E',
+        "GLY" => 'G',
+        "HIS" => 'H',
+        "ILE" => 'I',
+        "LEU" => 'L',
+        "LYS" => 'K',
+        "MET" => 'M',
+        "PHE" => 'F',
+        "PRO" => 'P',
+        "SER" => 'S',
+        "THR" => 'T',
+        "TRP" => 'W',
+        "TYR" => 'Y',
+        "VAL" => 'V',
+        "SEC" => 'U',
+        "PYL" => 'O',
+        "ASX" => 'B',
+        "GLX" => 'Z',
+        "UNK" => 'X',
+        // Common modified residues
+        "MSE" | "FME" => 'M',
+        "SEP" => 'S',
+        "TPO" => 'T',
+        "PTR" => 'Y',
+        "CSO" | "CSD" | "CME" | "OCS" | "CSS" => 'C',
+        "MLY" | "M3L" | "MLZ" | "ALY" | "KCX" | "LLP" => 'K',
+        "HYP" => 'P',
+        "CGU" => 'E',
+        "PCA" => 'Q',
+        "HIC" | "NEP" => 'H',
+        "NLE" => 'L',
+        "DAL" => 'A',
+        _ => return None,
+    };
+    Some(code)
+}
+
+impl ResidueView<'_> {
+    pub fn one_letter_code(&self) -> Option<char> {
+        one_letter_code(self.name())
+    }
+
+    /// Whether the residue belongs to the polymer: ATOM records, or HETATM records of a known modified amino acid.
+    pub fn is_polymer(&self) -> bool {
+        !self.is_hetatm() || self.one_letter_code().is_some()
+    }
+}
+
+impl<'a> ChainView<'a> {
+    pub fn polymer_residues(&self) -> impl Iterator<Item = &ResidueView<'a>> {
+        self.residues.iter().filter(|residue| residue.is_polymer())
+    }
+
+    /// Modeled one-letter sequence, `X` for non-standard polymer residues.
+    pub fn sequence(&self) -> String {
+        self.polymer_residues().map(|residue| residue.one_letter_code().unwrap_or('X')).collect()
+    }
+}
+
+impl ModelView<'_> {
+    /// Sequence of every chain with polymer residues, as (chain identifier, sequence).
+    pub fn sequences(&self) -> Vec<(&str, String)> {
+        self.chains
+            .iter()
+            .map(|chain| (chain.id(), chain.sequence()))
+            .filter(|(_, sequence)| !sequence.is_empty())
+            .collect()
+    }
+}
+
+impl ParsedPDB {
+    pub fn seqres_for(&self, chain_id: &str) -> Option<&SeqresChain> {
+        self.seqres.iter().find(|chain| chain.chain_id == chain_id)
+    }
+}
+
+/// Modeled residues of a chain aligned on its deposited sequence.
+#[derive(Debug, Clone)]
+pub struct SeqresAlignment {
+    /// Deposited one-letter sequence
+    pub deposited: String,
+    /// Modeled sequence laid on `deposited`, `-` where the residue has no coordinates
+    pub modeled: String,
+    /// For each deposited residue, index of the aligned residue in [`ChainView::polymer_residues`]
+    pub residue_map: Vec<Option<usize>>,
+    /// Deposited residues without coordinates
+    pub missing: usize,
+    /// Aligned residues whose one-letter codes differ
+    pub mismatches: usize,
+    /// Modeled residues absent from the deposited sequence
+    pub unaligned: usize,
+}
+
+const MATCH_SCORE: i32 = 2;
+const MISMATCH_SCORE: i32 = -1;
+const GAP_SCORE: i32 = -1;
+
+/// Globally aligns the modeled sequence of a chain on its deposited sequence (Needleman-Wunsch).
+///
+/// Ties are broken in favour of matches, then of deposited residues missing from
+/// the model, which is by far the most common difference.
+pub fn align_to_seqres(chain: &ChainView, seqres: &SeqresChain) -> SeqresAlignment {
+    let deposited: Vec<char> = seqres.sequence().chars().collect();
+    let modeled: Vec<char> = chain.sequence().chars().collect();
+    let (n, m) = (deposited.len(), modeled.len());
+
+    let width = m + 1;
+    let mut scores = vec![0i32; (n + 1) * width];
+    for i in 0..=n {
+        scores[i * width] = i as i32 * GAP_SCORE;
+    }
+    for (j, score) in scores[..width].iter_mut().enumerate() {
+        *score = j as i32 * GAP_SCORE;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let pair = if deposited[i - 1] == modeled[j - 1] { MATCH_SCORE } else { MISMATCH_SCORE };
+            let diagonal = scores[(i - 1) * width + j - 1] + pair;
+            let missing = scores[(i - 1) * width + j] + GAP_SCORE;
+            let extra = scores[i * width + j - 1] + GAP_SCORE;
+            scores[i * width + j] = diagonal.max(missing).max(extra);
+        }
+    }
+
+    let mut residue_map = vec![None; n];
+    let mut mismatches = 0;
+    let mut unaligned = 0;
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let score = scores[i * width + j];
+        if i > 0 && j > 0 {
+            let pair = if deposited[i - 1] == modeled[j - 1] { MATCH_SCORE } else { MISMATCH_SCORE };
+            if score == scores[(i - 1) * width + j - 1] + pair {
+                residue_map[i - 1] = Some(j - 1);
+                mismatches += (pair == MISMATCH_SCORE) as usize;
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+        if i > 0 && score == scores[(i - 1) * width + j] + GAP_SCORE {
+            i -= 1;
+        } else {
+            unaligned += 1;
+            j -= 1;
+        }
+    }
+
+    SeqresAlignment {
+        modeled: residue_map.iter().map(|r| r.map_or('-', |j| modeled[j])).collect(),
+        deposited: deposited.into_iter().collect(),
+        missing: residue_map.iter().filter(|r| r.is_none()).count(),
+        residue_map,
+        mismatches,
+        unaligned,
+    }
+}
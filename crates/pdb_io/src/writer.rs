@@ -0,0 +1,271 @@
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use crate::{AtomCoordinate, ParsedPDB};
+
+/// Serialises a structure as fixed-column PDB text.
+///
+/// SEQRES records come first. MODEL / ENDMDL records are written for ensembles,
+/// a TER record closes the polymer part of each chain and HETATM records follow it. Atom serial numbers
+/// are renumbered from 1.
+pub fn write_pdb<W: Write>(pdb: &ParsedPDB, writer: W) -> io::Result<()> {
+    write_pdb_records(pdb, writer, None)
+}
+
+/// Same as [`write_pdb`], with the B-factor column replaced by one value per atom,
+/// e.g. per-atom SASA. Values follow the order of [`ParsedPDB::atoms`].
+pub fn write_pdb_with_bfactors<W: Write>(pdb: &ParsedPDB, writer: W, bfactors: &[f32]) -> io::Result<()> {
+    write_pdb_records(pdb, writer, Some(bfactors))
+}
+
+/// Serialises a structure as an mmCIF `_atom_site` loop, preceded by the
+/// deposited sequence in `_pdbx_poly_seq_scheme` when there is one.
+pub fn write_mmcif<W: Write>(pdb: &ParsedPDB, writer: W) -> io::Result<()> {
+    write_mmcif_records(pdb, writer, None)
+}
+
+/// Same as [`write_mmcif`], with `B_iso_or_equiv` replaced by one value per atom.
+/// Values follow the order of [`ParsedPDB::atoms`].
+pub fn write_mmcif_with_bfactors<W: Write>(pdb: &ParsedPDB, writer: W, bfactors: &[f32]) -> io::Result<()> {
+    write_mmcif_records(pdb, writer, Some(bfactors))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Per-atom B-factors, either from the structure or from the caller.
+fn bfactor_source<'a>(pdb: &'a ParsedPDB, bfactors: Option<&'a [f32]>) -> io::Result<Box<dyn Iterator<Item = f32> + 'a>> {
+    match bfactors {
+        None => Ok(Box::new(pdb.atoms().map(|atom| atom.bfactor))),
+        Some(values) => {
+            let n_atoms = pdb.atoms().count();
+            if values.len() != n_atoms {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Got {} B-factor values for {} atoms", values.len(), n_atoms),
+                ));
+            }
+            Ok(Box::new(values.iter().copied()))
+        }
+    }
+}
+
+/// Formats a number right-justified in `width` columns, failing instead of shifting the following columns.
+fn fixed(value: f32, width: usize, precision: usize, field: &str) -> io::Result<String> {
+    let text = format!("{:>width$.precision$}", value, width = width, precision = precision);
+    if text.len() > width {
+        return Err(invalid_data(format!("{} {} does not fit in {} PDB columns", field, value, width)));
+    }
+    Ok(text)
+}
+
+/// Fails when a text field is longer than its PDB columns, which would shift the following ones.
+fn check_width(value: &str, width: usize, field: &str) -> io::Result<()> {
+    if value.chars().count() > width {
+        return Err(invalid_data(format!("{} {:?} does not fit in {} PDB columns", field, value, width)));
+    }
+    Ok(())
+}
+
+/// Atom names of one-letter elements start in column 14 unless they use all four columns.
+fn pdb_atom_name(atom: &AtomCoordinate) -> String {
+    if atom.atom_name.len() < 4 && atom.element.len() <= 1 {
+        format!(" {:<3}", atom.atom_name)
+    } else {
+        format!("{:<4}", atom.atom_name)
+    }
+}
+
+fn write_pdb_records<W: Write>(pdb: &ParsedPDB, mut writer: W, bfactors: Option<&[f32]>) -> io::Result<()> {
+    let mut bfactors = bfactor_source(pdb, bfactors)?;
+    let write_models = pdb.models.len() > 1 || pdb.models.iter().any(|m| m.serial != 1);
+
+    for chain in &pdb.seqres {
+        if chain.chain_id.chars().count() > 1 {
+            return Err(invalid_data(format!("Chain identifier {:?} does not fit in the PDB format", chain.chain_id)));
+        }
+        // 13 residues per record, names right-justified from column 20.
+        for (index, names) in chain.residues.chunks(13).enumerate() {
+            write!(writer, "SEQRES {:>3} {:1} {:>4} ", index + 1, chain.chain_id, chain.residues.len())?;
+            for name in names {
+                check_width(name, 3, "Residue name")?;
+                write!(writer, " {:>3}", name)?;
+            }
+            writeln!(writer)?;
+        }
+    }
+
+    for model in &pdb.models {
+        if write_models {
+            writeln!(writer, "MODEL     {:>4}", model.serial)?;
+        }
+        // Serial numbers restart in every model and wrap around the 5 available columns.
+        let mut serial = 0usize;
+
+        for chain in &model.chains {
+            if chain.id.chars().count() > 1 {
+                return Err(invalid_data(format!("Chain identifier {:?} does not fit in the PDB format", chain.id)));
+            }
+            let last_polymer = chain.atoms.iter().rposition(|atom| !atom.is_hetatm);
+
+            for (index, atom) in chain.atoms.iter().enumerate() {
+                let bfactor = bfactors.next().expect("one B-factor per atom");
+                serial += 1;
+                check_width(&atom.atom_name, 4, "Atom name")?;
+                check_width(&atom.residue_name, 3, "Residue name")?;
+                if !(-999..=9999).contains(&atom.residue_seq) {
+                    return Err(invalid_data(format!("Residue number {} does not fit in 4 PDB columns", atom.residue_seq)));
+                }
+
+                /*
+                Columns as in the parser: record, serial, name, altLoc, resName,
+                chainID, resSeq, iCode, x, y, z, occupancy, tempFactor, element.
+                */
+                writeln!(
+                    writer,
+                    "{:<6}{:>5} {}{}{:>3} {:1}{:>4}{}   {}{}{}{}{}          {:>2}",
+                    if atom.is_hetatm { "HETATM" } else { "ATOM" },
+                    serial % 100_000,
+                    pdb_atom_name(atom),
+                    atom.alt_loc.unwrap_or(' '),
+                    atom.residue_name,
+                    atom.chain_id,
+                    atom.residue_seq,
+                    atom.insertion_code.unwrap_or(' '),
+                    fixed(atom.x, 8, 3, "x coordinate")?,
+                    fixed(atom.y, 8, 3, "y coordinate")?,
+                    fixed(atom.z, 8, 3, "z coordinate")?,
+                    fixed(atom.occupancy, 6, 2, "Occupancy")?,
+                    fixed(bfactor, 6, 2, "B-factor")?,
+                    atom.element,
+                )?;
+
+                if Some(index) == last_polymer {
+                    serial += 1;
+                    writeln!(
+                        writer,
+                        "TER   {:>5}      {:>3} {:1}{:>4}{}",
+                        serial % 100_000,
+                        atom.residue_name,
+                        atom.chain_id,
+                        atom.residue_seq,
+                        atom.insertion_code.unwrap_or(' '),
+                    )?;
+                }
+            }
+        }
+
+        if write_models {
+            writeln!(writer, "ENDMDL")?;
+        }
+    }
+
+    writeln!(writer, "END")
+}
+
+/// Quotes a CIF value when needed; empty values become the `.` null.
+fn cif_value(value: &str) -> Cow<'_, str> {
+    let needs_quotes = value.chars().any(char::is_whitespace)
+        || value.starts_with(['_', '#', '$', '\'', '"', ';', '[', ']'])
+        || value == "."
+        || value == "?";
+
+    if value.is_empty() {
+        Cow::Borrowed(".")
+    } else if !needs_quotes {
+        Cow::Borrowed(value)
+    } else if value.contains("' ") || value.ends_with('\'') {
+        Cow::Owned(format!("\"{}\"", value))
+    } else {
+        Cow::Owned(format!("'{}'", value))
+    }
+}
+
+const ATOM_SITE_ITEMS: [&str; 19] = [
+    "group_PDB",
+    "id",
+    "type_symbol",
+    "label_atom_id",
+    "label_alt_id",
+    "label_comp_id",
+    "label_asym_id",
+    "label_seq_id",
+    "pdbx_PDB_ins_code",
+    "Cartn_x",
+    "Cartn_y",
+    "Cartn_z",
+    "occupancy",
+    "B_iso_or_equiv",
+    "auth_seq_id",
+    "auth_comp_id",
+    "auth_asym_id",
+    "auth_atom_id",
+    "pdbx_PDB_model_num",
+];
+
+fn write_mmcif_records<W: Write>(pdb: &ParsedPDB, mut writer: W, bfactors: Option<&[f32]>) -> io::Result<()> {
+    let mut bfactors = bfactor_source(pdb, bfactors)?;
+
+    writeln!(writer, "data_structure")?;
+    writeln!(writer, "#")?;
+
+    if !pdb.seqres.is_empty() {
+        writeln!(writer, "loop_")?;
+        for item in ["asym_id", "seq_id", "mon_id", "pdb_strand_id"] {
+            writeln!(writer, "_pdbx_poly_seq_scheme.{}", item)?;
+        }
+        for chain in &pdb.seqres {
+            let chain_id = cif_value(&chain.chain_id);
+            for (index, name) in chain.residues.iter().enumerate() {
+                writeln!(writer, "{} {} {} {}", chain_id, index + 1, cif_value(name), chain_id)?;
+            }
+        }
+        writeln!(writer, "#")?;
+    }
+
+    writeln!(writer, "loop_")?;
+    for item in ATOM_SITE_ITEMS.iter() {
+        writeln!(writer, "_atom_site.{}", item)?;
+    }
+
+    let mut serial = 0usize;
+    for model in &pdb.models {
+        for atom in model.atoms() {
+            let bfactor = bfactors.next().expect("one B-factor per atom");
+            serial += 1;
+
+            let atom_name = cif_value(&atom.atom_name);
+            let residue_name = cif_value(&atom.residue_name);
+            let chain_id = cif_value(&atom.chain_id);
+            // HETATM records have no label_seq_id in the PDBx dictionary.
+            let label_seq = if atom.is_hetatm { ".".to_string() } else { atom.residue_seq.to_string() };
+
+            writeln!(
+                writer,
+                "{} {} {} {} {} {} {} {} {} {:.3} {:.3} {:.3} {:.2} {:.2} {} {} {} {} {}",
+                if atom.is_hetatm { "HETATM" } else { "ATOM" },
+                serial,
+                cif_value(&atom.element),
+                atom_name,
+                atom.alt_loc.map_or_else(|| ".".to_string(), |c| c.to_string()),
+                residue_name,
+                chain_id,
+                label_seq,
+                atom.insertion_code.map_or_else(|| "?".to_string(), |c| c.to_string()),
+                atom.x,
+                atom.y,
+                atom.z,
+                atom.occupancy,
+                bfactor,
+                atom.residue_seq,
+                residue_name,
+                chain_id,
+                atom_name,
+                model.serial,
+            )?;
+        }
+    }
+
+    writeln!(writer, "#")
+}
@@ -1,12 +1,466 @@
 use std::error::Error;
-use pdb_io::parse_pdb; 
+use std::io::{Cursor, ErrorKind};
+use pdb_io::{
+    entry_id, parse_mmcif, parse_mmcif_from_reader_with_options, parse_mmcif_str, parse_pdb, parse_pdb_from_reader,
+    parse_pdb_from_reader_with_options, parse_pdb_str, parse_pdb_with_options, parse_structure,
+    align_to_seqres, one_letter_code, parse_structure_compact, parse_structure_compact_from_reader, parse_structure_from_reader, parse_structure_with_options,
+    write_mmcif, write_pdb, write_pdb_with_bfactors, write_structure, AltLocPolicy,
+    AtomCoordinate, BFactorPrecision, ParseOptions, ParsedPDB, PdbParseError, StructureFormat,
+    is_pae_file, pae_entry_id, read_pae, read_pae_from_reader, PaeError,
+};
+
+const TRIPEPTIDE: &str = "\
+HEADER    TEST
+ATOM      1  N   MET A   1      -1.458   0.000   0.000  1.00 91.20           N
+ATOM      2  CA  MET A   1       0.000   0.000   0.000  1.00 92.40           C
+ATOM      3  C   MET A   1       0.551   1.420   0.000  1.00 93.10           C
+ATOM      4  CA  LYS A   2       2.260   3.020   0.250  1.00 88.00           C
+ATOM      5  CA  PHE A   3       4.100   5.800  -0.750  1.00 70.50           C
+END
+";
 
 #[test]
 fn test_pdbio_basic() -> Result<(), Box<dyn Error>> {
-   
-    // Very very dégueulasse
-    let parsed_pdb = parse_pdb("/store/EQUIPES/BIM/MEMBERS/simon.herman/MicroStruct/test.pdb")?;
-    
-    println!("Parsed {} alpha-carbons", parsed_pdb.atoms.len());
+
+    let parsed_pdb = parse_pdb_str(TRIPEPTIDE)?;
+
+    let atoms: Vec<_> = parsed_pdb.atoms().collect();
+    assert_eq!(atoms.len(), 5);
+    assert_eq!(atoms.iter().filter(|a| a.atom_name == "CA").count(), 3);
+
+    let last = atoms[4];
+    assert_eq!((last.residue_name.as_str(), last.residue_seq), ("PHE", 3));
+    assert_eq!((last.x, last.y, last.z), (4.1, 5.8, -0.75));
+    assert_eq!(last.bfactor, 70.5);
+    Ok(())
+}
+
+#[test]
+fn test_pdbio_reader_and_str_entry_points() -> Result<(), Box<dyn Error>> {
+
+    // Any BufRead works, e.g. stdin or a buffer fetched from elsewhere.
+    let from_reader = parse_pdb_from_reader(Cursor::new(TRIPEPTIDE.as_bytes().to_vec()))?;
+    assert_eq!(from_reader.atoms().count(), 5);
+
+    let cif = std::fs::read_to_string(fixture("two_models.cif"))?;
+    assert_eq!(parse_mmcif_str(&cif)?.models.len(), 2);
+    Ok(())
+}
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+#[test]
+fn test_pdbio_chain_icode_altloc() -> Result<(), Box<dyn Error>> {
+
+    let parsed_pdb = parse_pdb(fixture("altloc_two_chains.pdb"))?;
+    let atoms: Vec<_> = parsed_pdb.atoms().collect();
+
+    // Default policy keeps the first CB conformer only.
+    assert_eq!(atoms.len(), 7);
+    let cb = atoms.iter().find(|a| a.atom_name == "CB").unwrap();
+    assert_eq!(cb.alt_loc, Some('A'));
+    assert_eq!(cb.occupancy, 0.40);
+    assert_eq!(cb.element, "C");
+
+    let inserted = atoms[4];
+    assert_eq!(inserted.residue_seq, 2);
+    assert_eq!(inserted.insertion_code, Some('A'));
+    assert_eq!(atoms[3].insertion_code, None);
+
+    let chains: Vec<&str> = atoms.iter().map(|a| a.chain_id.as_str()).collect();
+    assert_eq!(chains, ["A", "A", "A", "A", "A", "B", "B"]);
+    assert_eq!(parsed_pdb.models.len(), 1);
+    assert_eq!(parsed_pdb.models[0].chains.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_pdbio_altloc_policies() -> Result<(), Box<dyn Error>> {
+
+    let highest = parse_pdb_with_options(
+        fixture("altloc_two_chains.pdb"),
+        &ParseOptions { altloc: AltLocPolicy::HighestOccupancy, ..Default::default() },
+    )?;
+    let atoms: Vec<_> = highest.atoms().collect();
+    let cb = atoms.iter().find(|a| a.atom_name == "CB").unwrap();
+    assert_eq!(cb.alt_loc, Some('B'));
+    // The selected conformer keeps the position of the first one.
+    assert_eq!(atoms[2].atom_name, "CB");
+
+    let all = parse_pdb_with_options(
+        fixture("altloc_two_chains.pdb"),
+        &ParseOptions { altloc: AltLocPolicy::KeepAll, ..Default::default() },
+    )?;
+    assert_eq!(all.atoms().count(), 8);
+    Ok(())
+}
+
+#[test]
+fn test_pdbio_models_hetatm_ter() -> Result<(), Box<dyn Error>> {
+
+    let parsed_pdb = parse_pdb(fixture("nmr_ensemble.pdb"))?;
+
+    let serials: Vec<i32> = parsed_pdb.models.iter().map(|m| m.serial).collect();
+    assert_eq!(serials, [1, 2]);
+
+    let model = &parsed_pdb.models[1];
+    let chain_ids: Vec<&str> = model.chains.iter().map(|c| c.id.as_str()).collect();
+    assert_eq!(chain_ids, ["A", "B"]);
+
+    // Ligands and waters after TER stay with chain A, flagged as HETATM.
+    let chain_a = &model.chains[0];
+    assert_eq!(chain_a.atoms.len(), 6);
+    let mse: Vec<_> = chain_a.atoms.iter().filter(|a| a.residue_name == "MSE").collect();
+    assert_eq!(mse.len(), 2);
+    assert!(mse.iter().all(|a| a.is_hetatm));
+    assert!(!chain_a.atoms[0].is_hetatm);
+    assert_eq!(chain_a.atoms[0].x, 0.5);
     Ok(())
 }
+
+#[test]
+fn test_mmcif_atom_site() -> Result<(), Box<dyn Error>> {
+
+    let parsed = parse_structure(fixture("two_models.cif"))?;
+
+    let serials: Vec<i32> = parsed.models.iter().map(|m| m.serial).collect();
+    assert_eq!(serials, [1, 2]);
+
+    let model = &parsed.models[0];
+    let chain_ids: Vec<&str> = model.chains.iter().map(|c| c.id.as_str()).collect();
+    assert_eq!(chain_ids, ["A", "B"]);
+
+    // auth_* identifiers win, the water joins chain A like in the PDB reader.
+    let chain_a = &model.chains[0];
+    assert_eq!(chain_a.atoms.len(), 6);
+    let water = chain_a.atoms.last().unwrap();
+    assert_eq!((water.residue_name.as_str(), water.residue_seq), ("HOH", 201));
+    assert!(water.is_hetatm);
+
+    let cb = chain_a.atoms.iter().find(|a| a.atom_name == "CB").unwrap();
+    assert_eq!((cb.alt_loc, cb.occupancy), (Some('A'), 0.40));
+
+    let ser = &chain_a.atoms[3];
+    assert_eq!((ser.residue_name.as_str(), ser.insertion_code), ("SER", Some('A')));
+    assert_eq!(chain_a.atoms[0].insertion_code, None);
+
+    assert_eq!(model.chains[1].atoms[1].atom_name, "C1'");
+    assert_eq!(parsed.models[1].atoms().count(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_compressed_inputs() -> Result<(), Box<dyn Error>> {
+
+    let plain = parse_structure(fixture("nmr_ensemble.pdb"))?;
+    let gzipped = parse_structure(fixture("nmr_ensemble.pdb.gz"))?;
+    assert_eq!(gzipped.models.len(), plain.models.len());
+    assert_eq!(gzipped.atoms().count(), plain.atoms().count());
+
+    // Detection relies on magic bytes, not on the file name.
+    let bzipped = parse_mmcif(fixture("two_models.cif.bz2"))?;
+    assert_eq!(bzipped.atoms().count(), parse_mmcif(fixture("two_models.cif"))?.atoms().count());
+    Ok(())
+}
+
+#[test]
+fn test_compound_extensions() {
+    assert_eq!(StructureFormat::from_extension("x/AF-P1-F1-model_v4.cif.gz"), Some(StructureFormat::Mmcif));
+    assert_eq!(StructureFormat::from_extension("x/1abc.pdb.bz2"), Some(StructureFormat::Pdb));
+    assert_eq!(StructureFormat::from_extension("x/pdb1abc.ent.gz"), Some(StructureFormat::Pdb));
+    assert_eq!(StructureFormat::from_extension("x/notes.txt.gz"), None);
+
+    assert_eq!(entry_id("x/AF-P1-F1-model_v4.cif.gz").as_deref(), Some("AF-P1-F1-model_v4"));
+    assert_eq!(entry_id("x/1abc.pdb").as_deref(), Some("1abc"));
+}
+
+#[test]
+fn test_predicted_aligned_error() -> Result<(), Box<dyn Error>> {
+
+    // AlphaFold DB v4: a list holding one record with the matrix.
+    let path = fixture("AF-TEST-F1-predicted_aligned_error_v4.json");
+    let pae = read_pae(&path)?;
+    assert_eq!(pae.size, 3);
+    assert_eq!((pae.get(0, 1), pae.get(1, 0), pae.get(2, 2)), (3.5, 4.0, 0.25));
+    assert_eq!(pae.max, Some(31.75));
+
+    // The PAE file pairs with the model through its entry ID.
+    assert!(is_pae_file(&path));
+    assert_eq!(pae_entry_id(&path), entry_id(fixture("AF-TEST-F1-model_v4.pdb")));
+    assert_eq!(pae_entry_id("x/1abc_pae.json.gz").as_deref(), Some("1abc"));
+    assert!(!is_pae_file("x/AF-P1-F1-confidence_v4.json"));
+    assert!(!is_pae_file("x/1abc_pae.pdb"));
+
+    // AlphaFold DB v1 flat lists and ColabFold records without the list.
+    let flat = r#"[{"residue1": [1, 1, 2, 2], "residue2": [1, 2, 1, 2], "distance": [0.1, 5.5, 6.5, 0.2]}]"#;
+    let pae = read_pae_from_reader(flat.as_bytes())?;
+    assert_eq!((pae.size, pae.values.clone(), pae.max), (2, vec![0.1, 5.5, 6.5, 0.2], None));
+    let colabfold = r#"{"pae": [[0.0, 1.5], [2.5, 0.0]], "max_pae": 30.0, "plddt": [90.0, 80.0]}"#;
+    assert_eq!(read_pae_from_reader(colabfold.as_bytes())?.get(1, 0), 2.5);
+
+    assert!(matches!(read_pae_from_reader(r#"{"pae": [[0.0, 1.5], [2.5]]}"#.as_bytes()), Err(PaeError::NotSquare { row: 1, .. })));
+    assert!(matches!(read_pae_from_reader(r#"{"plddt": [90.0]}"#.as_bytes()), Err(PaeError::MissingMatrix)));
+    let truncated = r#"{"residue1": [1, 1, 2, 2], "residue2": [1, 2, 1, 2], "distance": [0.1, 5.5, 6.5]}"#;
+    assert!(matches!(
+        read_pae_from_reader(truncated.as_bytes()),
+        Err(PaeError::MismatchedLists { residue1: 4, residue2: 4, distance: 3 })
+    ));
+    assert!(matches!(read_pae_from_reader("[{".as_bytes()), Err(PaeError::InvalidJson { .. })));
+    Ok(())
+}
+
+#[test]
+fn test_parse_structure_from_reader() -> Result<(), Box<dyn Error>> {
+
+    // Compressed bytes as they come out of an archive member.
+    let bytes = std::fs::read(fixture("nmr_ensemble.pdb.gz"))?;
+    let parsed = parse_structure_from_reader(&bytes[..], StructureFormat::Pdb, &ParseOptions::default())?;
+    assert_eq!(parsed.models.len(), 2);
+    assert_eq!(parsed.models[0].atoms().count(), 7);
+    Ok(())
+}
+
+#[test]
+fn test_parse_errors_report_line_and_columns() {
+
+    // Third ATOM record has a corrupted y coordinate, the last one is truncated.
+    let broken = TRIPEPTIDE
+        .replace("   1.420   0.000  1.00 93.10", "   1.4x0   0.000  1.00 93.10")
+        .replace("  4.100   5.800  -0.750  1.00 70.50           C", "  4.100   5.8");
+
+    match parse_pdb_str(&broken) {
+        Err(PdbParseError::InvalidField { line, field, value, columns, content }) => {
+            assert_eq!((line, field, value.as_str()), (4, "y coordinate", "1.4x0"));
+            assert_eq!(columns, 38..46);
+            assert!(content.starts_with("ATOM      3  C   MET"));
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+
+    let lenient = ParseOptions { lenient: true, ..Default::default() };
+    let parsed = parse_pdb_from_reader_with_options(broken.as_bytes(), &lenient).unwrap();
+    assert_eq!(parsed.atoms().count(), 3);
+    let lines: Vec<_> = parsed.warnings.iter().map(|w| w.line()).collect();
+    assert_eq!(lines, [Some(4), Some(6)]);
+    assert!(matches!(parsed.warnings[1], PdbParseError::MissingField { field: "y coordinate", .. }));
+    assert!(parsed.warnings[1].to_string().starts_with("Line 6: missing y coordinate (columns 39-46)"));
+}
+
+#[test]
+fn test_mmcif_errors_and_missing_file() {
+
+    let cif = std::fs::read_to_string(fixture("two_models.cif")).unwrap();
+    let broken = cif.replace("ATOM   8  C  CA  . ALA B 1 1 ? 0.000 ", "ATOM   8  C  CA  . ALA B 1 1 ? abc ");
+
+    match parse_mmcif_str(&broken) {
+        Err(PdbParseError::InvalidField { line, field, value, .. }) => {
+            assert_eq!((line, field, value.as_str()), (38, "x coordinate", "abc"));
+        }
+        other => panic!("Unexpected result: {:?}", other),
+    }
+
+    let lenient = ParseOptions { lenient: true, ..Default::default() };
+    let parsed = parse_mmcif_from_reader_with_options(broken.as_bytes(), &lenient).unwrap();
+    assert_eq!(parsed.warnings.len(), 1);
+    assert_eq!(parsed.models[0].chains[1].atoms.len(), 1);
+
+    assert!(matches!(parse_pdb(fixture("does_not_exist.pdb")), Err(PdbParseError::Open { .. })));
+}
+
+fn assert_same_structure(expected: &ParsedPDB, actual: &ParsedPDB) {
+    let layout = |pdb: &ParsedPDB| -> Vec<(i32, Vec<String>)> {
+        pdb.models.iter().map(|m| (m.serial, m.chains.iter().map(|c| c.id.clone()).collect())).collect()
+    };
+    assert_eq!(layout(expected), layout(actual));
+    assert_eq!(expected.seqres, actual.seqres);
+    assert_eq!(expected.atoms().count(), actual.atoms().count());
+
+    for (a, b) in expected.atoms().zip(actual.atoms()) {
+        assert_eq!(
+            (&a.atom_name, a.alt_loc, &a.residue_name, &a.chain_id, a.residue_seq, a.insertion_code),
+            (&b.atom_name, b.alt_loc, &b.residue_name, &b.chain_id, b.residue_seq, b.insertion_code),
+        );
+        assert_eq!((&a.element, a.is_hetatm), (&b.element, b.is_hetatm));
+        assert_eq!((a.x, a.y, a.z, a.occupancy, a.bfactor), (b.x, b.y, b.z, b.occupancy, b.bfactor));
+    }
+}
+
+#[test]
+fn test_writers_round_trip() -> Result<(), Box<dyn Error>> {
+
+    let keep_all = ParseOptions { altloc: AltLocPolicy::KeepAll, ..Default::default() };
+    for name in ["nmr_ensemble.pdb", "altloc_two_chains.pdb", "two_models.cif", "seqres_gaps.pdb"] {
+        let parsed = parse_structure_from_reader(
+            Cursor::new(std::fs::read(fixture(name))?),
+            StructureFormat::from_path(name),
+            &keep_all,
+        )?;
+
+        for format in [StructureFormat::Pdb, StructureFormat::Mmcif] {
+            let mut buffer = Vec::new();
+            write_structure(&parsed, &mut buffer, format)?;
+            let reparsed = parse_structure_from_reader(&buffer[..], format, &keep_all)?;
+            assert_same_structure(&parsed, &reparsed);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_pdb_writer_columns_and_custom_bfactors() -> Result<(), Box<dyn Error>> {
+
+    let mut parsed = parse_pdb_str(TRIPEPTIDE)?;
+    let mut buffer = Vec::new();
+    write_pdb(&parsed, &mut buffer)?;
+    let text = String::from_utf8(buffer)?;
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], TRIPEPTIDE.lines().nth(1).unwrap());
+    assert_eq!(lines[5], "TER       6      PHE A   3 ");
+    assert_eq!(lines[6], "END");
+
+    // Per-atom values, e.g. SASA, replace the B-factor column.
+    let sasa = [12.5, 0.0, 3.25, 101.0, 999.99];
+    let mut buffer = Vec::new();
+    write_pdb_with_bfactors(&parsed, &mut buffer, &sasa)?;
+    let rewritten = parse_pdb_str(std::str::from_utf8(&buffer)?)?;
+    let bfactors: Vec<f32> = rewritten.atoms().map(|a| a.bfactor).collect();
+    assert_eq!(bfactors, sasa);
+
+    assert!(write_pdb_with_bfactors(&parsed, &mut Vec::new(), &sasa[..2]).is_err());
+    assert!(write_pdb_with_bfactors(&parsed, &mut Vec::new(), &[0.0, 0.0, 0.0, 0.0, 1000.0]).is_err());
+
+    // Fields wider than their columns, common in mmCIF input, fail instead of shifting the line.
+    let too_wide = |edit: fn(&mut AtomCoordinate)| {
+        let mut pdb = parse_pdb_str(TRIPEPTIDE).unwrap();
+        edit(&mut pdb.models[0].chains[0].atoms[1]);
+        write_pdb(&pdb, &mut Vec::new()).map_err(|e| e.kind())
+    };
+    assert_eq!(too_wide(|atom| atom.residue_name = "ABCD".to_string()), Err(ErrorKind::InvalidData));
+    assert_eq!(too_wide(|atom| atom.residue_seq = 10_000), Err(ErrorKind::InvalidData));
+    assert_eq!(too_wide(|atom| atom.residue_seq = -1000), Err(ErrorKind::InvalidData));
+    assert_eq!(too_wide(|atom| atom.atom_name = "CA123".to_string()), Err(ErrorKind::InvalidData));
+    assert_eq!(too_wide(|atom| atom.residue_seq = -999), Ok(()));
+
+    // Multi-character mmCIF chain identifiers only fit the mmCIF writer.
+    parsed.models[0].chains[0].id = "AA".to_string();
+    assert!(write_pdb(&parsed, &mut Vec::new()).is_err());
+    assert!(write_mmcif(&parsed, &mut Vec::new()).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_hierarchy_view() -> Result<(), Box<dyn Error>> {
+
+    let keep_all = ParseOptions { altloc: AltLocPolicy::KeepAll, ..Default::default() };
+    let parsed = parse_pdb_with_options(fixture("altloc_two_chains.pdb"), &keep_all)?;
+    let view = parsed.view();
+    let model = &view.models[0];
+
+    let residues: Vec<(&str, i32, Option<char>)> =
+        model.residues().map(|r| (r.chain_id(), r.residue_seq(), r.insertion_code())).collect();
+    assert_eq!(residues, [("A", 1, None), ("A", 2, None), ("A", 2, Some('A')), ("B", 1, None), ("B", 2, None)]);
+
+    // Both CB conformers stay in MET 1, which still has a single CA.
+    let met = model.residue("A", 1, None).unwrap();
+    assert_eq!((met.name(), met.atoms.len()), ("MET", 4));
+    assert_eq!(model.ca_atoms().len(), 5);
+
+    assert_eq!(model.residue("A", 2, Some('A')).unwrap().name(), "SER");
+    assert_eq!(model.residue("B", 2, None).unwrap().name(), "LEU");
+    assert!(model.residue("C", 1, None).is_none());
+
+    // Only MET 1 of the tripeptide has its N, CA and C atoms.
+    let tripeptide = parse_pdb_str(TRIPEPTIDE)?;
+    let backbone: Vec<_> = tripeptide.models[0].view().backbone().collect();
+    assert_eq!(backbone.len(), 1);
+    assert_eq!((backbone[0].n.x, backbone[0].c.y), (-1.458, 1.42));
+    assert!(backbone[0].o.is_none());
+
+    // Waters and ions after TER form their own residues, with no alpha-carbon.
+    let ensemble = parse_pdb(fixture("nmr_ensemble.pdb"))?;
+    let chain_a = &ensemble.models[0].view().chains[0];
+    let names: Vec<&str> = chain_a.residues.iter().map(|r| r.name()).collect();
+    assert_eq!(names, ["ALA", "MSE", "GLY", "CA", "HOH"]);
+    assert_eq!(chain_a.ca_atoms().len(), 3);
+    assert!(chain_a.residue(2, None).unwrap().is_hetatm());
+    Ok(())
+}
+
+#[test]
+fn test_compact_matches_parsed() -> Result<(), Box<dyn Error>> {
+
+    let policies = [AltLocPolicy::First, AltLocPolicy::HighestOccupancy, AltLocPolicy::KeepAll];
+    for name in ["nmr_ensemble.pdb", "altloc_two_chains.pdb", "two_models.cif", "two_models.cif.bz2"] {
+        for altloc in policies {
+            let options = ParseOptions { altloc, ..Default::default() };
+            let parsed = parse_structure_with_options(fixture(name), &options)?;
+            let compact = parse_structure_compact(fixture(name), &options)?;
+            assert_same_structure(&parsed, &compact.to_parsed());
+        }
+    }
+
+    // Ligands and waters listed after chain B still end up inside chain A.
+    let compact = parse_structure_compact(fixture("nmr_ensemble.pdb"), &ParseOptions::default())?;
+    assert_eq!(compact.models.len(), 2);
+    let model = &compact.models[1];
+    assert_eq!(compact.model_atoms(model), 7..14);
+    let chain_a = &compact.chains[model.chains.start];
+    assert_eq!(compact.names.get(chain_a.id), "A");
+    assert_eq!(compact.residue_name(chain_a.atoms.end - 1), "HOH");
+    assert_eq!(compact.coords[chain_a.atoms.start], [0.5, 0.0, 0.0]);
+
+    // Names are stored once per structure.
+    let ca = compact.names.find("CA").unwrap();
+    assert_eq!(compact.atom_names.iter().filter(|&&id| id == ca).count(), 10);
+    assert!(compact.names.len() < 20);
+    Ok(())
+}
+
+#[test]
+fn test_compact_half_precision_bfactors() -> Result<(), Box<dyn Error>> {
+
+    let options = ParseOptions { bfactor_precision: BFactorPrecision::F16, ..Default::default() };
+    let compact = parse_structure_compact_from_reader(TRIPEPTIDE.as_bytes(), StructureFormat::Pdb, &options)?;
+    assert_eq!(compact.len(), 5);
+    assert_eq!(compact.bfactors.len(), 5);
+
+    let expected = [91.20, 92.40, 93.10, 88.00, 70.50];
+    for (i, value) in expected.iter().enumerate() {
+        assert!((compact.bfactors.get(i) - value).abs() < 0.05);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_sequences_and_seqres_alignment() -> Result<(), Box<dyn Error>> {
+
+    assert_eq!(one_letter_code("TRP"), Some('W'));
+    assert_eq!(one_letter_code("SEP"), Some('S'));
+    assert_eq!(one_letter_code("HOH"), None);
+
+    // The modified MSE is part of the sequence, the water after TER is not.
+    let parsed = parse_pdb(fixture("seqres_gaps.pdb"))?;
+    let model = parsed.models[0].view();
+    assert_eq!(model.sequences(), [("A", "ASMKL".to_string())]);
+
+    let seqres = parsed.seqres_for("A").unwrap();
+    assert_eq!(seqres.residues.len(), 8);
+    let alignment = align_to_seqres(&model.chains[0], seqres);
+    assert_eq!(alignment.deposited, "MASMGKLE");
+    assert_eq!(alignment.modeled, "-ASM-KL-");
+    assert_eq!((alignment.missing, alignment.mismatches, alignment.unaligned), (3, 0, 0));
+    assert_eq!(alignment.residue_map[3], Some(2));
+
+    // mmCIF: deposited sequence from _pdbx_poly_seq_scheme, keyed by the author chain.
+    let parsed = parse_structure(fixture("two_models.cif"))?;
+    let chain_ids: Vec<&str> = parsed.seqres.iter().map(|c| c.chain_id.as_str()).collect();
+    assert_eq!(chain_ids, ["A", "B"]);
+    let model = parsed.models[0].view();
+    assert_eq!(model.sequences(), [("A", "MSM".to_string()), ("B", "A".to_string())]);
+    let alignment = align_to_seqres(&model.chains[0], parsed.seqres_for("A").unwrap());
+    assert_eq!((alignment.modeled.as_str(), alignment.missing), ("MSM-", 1));
+    Ok(())
+}
+
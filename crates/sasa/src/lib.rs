@@ -1,5 +1,6 @@
 //! RustSASA is a Rust library for computing the absolute solvent accessible surface area (ASA/SASA) of each atom in a given protein structure using the Shrake-Rupley algorithm[1].
 mod consts;
+#[cfg(test)]
 mod test;
 mod utils;
 
@@ -90,6 +91,14 @@ pub enum SASACalcError {
     FailedToGetResidueName,
 }
 
+/// Van der Waals radius, in Å, of the element with this symbol (case insensitive), as used by [calculate_sasa].
+pub fn van_der_waals_radius(element: &str) -> Option<f32> {
+    pdbtbx::Element::from_symbol(element)?
+        .atomic_radius()
+        .van_der_waals
+        .map(|radius| radius as f32)
+}
+
 impl RTreeObject for Atom {
     type Envelope = AABB<[f32; 3]>;
 
@@ -160,11 +169,14 @@ fn is_accessible_rstar(
 /// ## Example using pdbtbx:
 /// ```
 /// use nalgebra::{Point3, Vector3};
-/// use pdbtbx::StrictnessLevel;
+/// use pdbtbx::{Format, ReadOptions, StrictnessLevel};
 /// use sasa::{Atom, calculate_sasa_internal};
-/// let (mut pdb, _errors) = pdbtbx::open(
-///             "/store/EQUIPES/BIM/MEMBERS/simon.herman/MicroStruct/data/example.cif",
-///         ).unwrap();
+/// let text = "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00 20.00           C\nEND\n";
+/// let (mut pdb, _errors) = ReadOptions::default()
+///     .set_format(Format::Pdb)
+///     .set_level(StrictnessLevel::Loose)
+///     .read_raw(std::io::BufReader::new(text.as_bytes()))
+///     .unwrap();
 /// let mut atoms = vec![];
 /// for atom in pdb.atoms() {
 ///     atoms.push(Atom {
@@ -175,6 +187,7 @@ fn is_accessible_rstar(
 ///     })
 ///  }
 ///  let sasa = calculate_sasa_internal(&atoms, None, None);
+///  assert_eq!(sasa.len(), 1);
 /// ```
 pub fn calculate_sasa_internal(
     atoms: &[Atom],
@@ -226,12 +239,16 @@ pub fn calculate_sasa_internal(
 /// If you want more fine-grained control you may want to use [calculate_sasa_internal] instead.
 /// ## Example
 /// ```
-/// use pdbtbx::StrictnessLevel;
+/// use pdbtbx::{Format, ReadOptions, StrictnessLevel};
 /// use sasa::{Atom, calculate_sasa, calculate_sasa_internal, SASALevel};
-/// let (mut pdb, _errors) = pdbtbx::open(
-///             "/store/EQUIPES/BIM/MEMBERS/simon.herman/MicroStruct/data/example.cif",
-/// ).unwrap();
+/// let text = "ATOM      1  CA  ALA A   1       0.000   0.000   0.000  1.00 20.00           C\nEND\n";
+/// let (mut pdb, _errors) = ReadOptions::default()
+///     .set_format(Format::Pdb)
+///     .set_level(StrictnessLevel::Loose)
+///     .read_raw(std::io::BufReader::new(text.as_bytes()))
+///     .unwrap();
 /// let result = calculate_sasa(&pdb,None,None,SASALevel::Residue);
+/// assert!(result.is_ok());
 /// ```
 pub fn calculate_sasa(
     pdb: &PDB,
@@ -319,7 +336,7 @@ pub fn calculate_sasa(
         }
     }
     let atom_sasa = calculate_sasa_internal(&atoms, probe_radius, n_points);
-    return match level {
+    match level {
         SASALevel::Atom => Ok(SASAResult::Atom(atom_sasa)),
         SASALevel::Chain => {
             let mut chain_sasa = vec![];
@@ -396,5 +413,5 @@ pub fn calculate_sasa(
                 non_polar_total,
             }))
         }
-    };
+    }
 }
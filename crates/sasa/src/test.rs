@@ -243,10 +243,110 @@ mod tests {
         SASAResult,
     };
     use nalgebra::Point3;
+    use pdbtbx::{Format, ReadOptions, StrictnessLevel, PDB};
+    use std::io::BufReader;
     use std::time::Instant;
 
+    /// SASA of an isolated atom: the whole sphere of radius `radius + 1.4` is accessible.
+    fn isolated_sasa(radius: f32) -> f32 {
+        4.0 * std::f32::consts::PI * (radius + 1.4).powi(2)
+    }
+
+    fn atom(id: usize, x: f32, radius: f32) -> Atom {
+        Atom {
+            position: Point3::new(x, 0.0, 0.0),
+            radius,
+            id,
+            parent_id: None,
+        }
+    }
+
+    /// A serine (polar) and an alanine (non-polar) N-CA pair in chain A, 40 A apart.
+    fn two_residue_pdb() -> PDB {
+        let text: String = [(1, "N", "SER", 1, 0.0), (2, "CA", "SER", 1, 1.46), (3, "N", "ALA", 2, 40.0), (4, "CA", "ALA", 2, 41.46)]
+            .iter()
+            .map(|&(serial, name, residue, seq, x)| {
+                format!(
+                    "ATOM  {:>5}  {:<3} {} A{:>4}    {:>8.3}{:>8.3}{:>8.3}  1.00 20.00           {}\n",
+                    serial, name, residue, seq, x, 0.0, 0.0, &name[..1]
+                )
+            })
+            .collect::<String>()
+            + "END\n";
+        let (pdb, _errors) = ReadOptions::default()
+            .set_format(Format::Pdb)
+            .set_level(StrictnessLevel::Loose)
+            .read_raw(BufReader::new(text.as_bytes()))
+            .unwrap();
+        pdb
+    }
+
     #[test]
     fn internal_test() {
+        // Far apart atoms are fully exposed, close ones hide part of each other.
+        let atoms = [atom(1, 0.0, 1.7), atom(2, 20.0, 1.55), atom(3, 22.0, 1.55)];
+        let start = Instant::now();
+        let sasa = calculate_sasa_internal(&atoms, None, None);
+        let duration = start.elapsed();
+        assert!((sasa[0] - isolated_sasa(1.7)).abs() < 1e-3);
+        for value in &sasa[1..] {
+            assert!(*value > 0.5 * isolated_sasa(1.55) && *value < isolated_sasa(1.55));
+        }
+        println!("Time elapsed (INTERNAL): {:?}", duration);
+
+        // An atom inside a tight shell of neighbours has no accessible surface.
+        let mut buried = vec![atom(0, 0.0, 1.7)];
+        for (id, offset) in [[1.5, 0.0, 0.0], [-1.5, 0.0, 0.0], [0.0, 1.5, 0.0], [0.0, -1.5, 0.0], [0.0, 0.0, 1.5], [0.0, 0.0, -1.5]]
+            .iter()
+            .enumerate()
+        {
+            buried.push(Atom {
+                position: Point3::new(offset[0], offset[1], offset[2]),
+                ..atom(id + 1, 0.0, 1.7)
+            });
+        }
+        assert_eq!(calculate_sasa_internal(&buried, None, None)[0], 0.0);
+    }
+
+    #[test]
+    fn external_test() {
+        let pdb = two_residue_pdb();
+        let protein_sasa = calculate_sasa(&pdb, None, None, SASALevel::Protein).unwrap();
+        let chain_sasa = calculate_sasa(&pdb, None, None, SASALevel::Chain).unwrap();
+        let residue_sasa = calculate_sasa(&pdb, None, None, SASALevel::Residue).unwrap();
+
+        let start = Instant::now();
+        let atom_sasa = calculate_sasa(&pdb, None, None, SASALevel::Atom).unwrap();
+        let duration = start.elapsed();
+        println!("Time elapsed (ATOM): {:?}", duration);
+
+        let SASAResult::Atom(atoms) = atom_sasa else { panic!("expected atom values") };
+        assert_eq!(atoms.len(), 4);
+        let SASAResult::Residue(residues) = residue_sasa else { panic!("expected residue values") };
+        assert_eq!(residues.len(), 2);
+        assert!(residues[0].is_polar && !residues[1].is_polar);
+        assert!((residues[0].value - (atoms[0] + atoms[1])).abs() < 1e-3);
+
+        assert_eq!(
+            protein_sasa,
+            SASAResult::Protein(ProteinResult {
+                global_total: atoms.iter().sum(),
+                polar_total: residues[0].value,
+                non_polar_total: residues[1].value,
+            })
+        );
+        assert_eq!(
+            chain_sasa,
+            Chain(vec![ChainResult {
+                name: "A".to_string(),
+                value: atoms.iter().sum(),
+            }])
+        );
+    }
+
+    #[test]
+    #[ignore = "reference values of example.cif, a 2622-atom structure kept outside the repository"]
+    fn example_reference_values() {
         let (pdb, _errors) = pdbtbx::open("/store/EQUIPES/BIM/MEMBERS/simon.herman/MicroStruct/data/example.cif").unwrap();
         let mut atoms = vec![];
         for atom in pdb.atoms() {
@@ -266,41 +366,15 @@ mod tests {
                 parent_id: None,
             })
         }
-        let start = Instant::now();
         let sasa = calculate_sasa_internal(&atoms, None, None);
-        let duration = start.elapsed();
         assert_eq!(sasa, FIXED_FOR_EXAMPLE.to_vec());
-        println!("Time elapsed (INTERNAL): {:?}", duration);
-    }
-    #[test]
-    fn external_test() {
-        let (pdb, _errors) = pdbtbx::open("/store/EQUIPES/BIM/MEMBERS/simon.herman/MicroStruct/data/example.cif").unwrap();
-        let protein_sasa = calculate_sasa(&pdb, None, None, SASALevel::Protein).unwrap();
-        let chain_sasa = calculate_sasa(&pdb, None, None, SASALevel::Chain).unwrap();
-
-        let _residue_sasa = calculate_sasa(&pdb, None, None, SASALevel::Residue).unwrap();
-
-        let start = Instant::now();
-        let _atom_sasa = calculate_sasa(&pdb, None, None, SASALevel::Atom).unwrap();
-        let duration = start.elapsed();
-        println!("Time elapsed (ATOM): {:?}", duration);
-
         assert_eq!(
-            protein_sasa,
+            calculate_sasa(&pdb, None, None, SASALevel::Protein).unwrap(),
             SASAResult::Protein(ProteinResult {
                 global_total: 20268.004,
                 polar_total: 4265.969,
                 non_polar_total: 16002.031
             })
         );
-        assert_eq!(
-            chain_sasa,
-            Chain(vec![ChainResult {
-                name: "A".to_string(),
-                value: 20268.004
-            }])
-        );
-        println!("PROTEIN SASA {:?}", protein_sasa);
-        println!("CHAIN SASA {:?}", chain_sasa);
     }
 }
@@ -7,18 +7,15 @@ pub fn simd_sum(values: &[f32]) -> f32 {
 
     let sum = chunks.fold([0.0f32; LANES], |mut acc, chunk| {
         let chunk: [f32; LANES] = chunk.try_into().unwrap();
-        for i in 0..LANES {
-            acc[i] += chunk[i];
+        for (a, c) in acc.iter_mut().zip(chunk.iter()) {
+            *a += c;
         }
         acc
     });
 
     let remainder: f32 = remainder.iter().copied().sum();
 
-    let mut reduced = 0.0f32;
-    for i in 0..LANES {
-        reduced += sum[i];
-    }
+    let reduced: f32 = sum.iter().sum();
     reduced + remainder
 }
 